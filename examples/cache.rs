@@ -52,7 +52,7 @@ fn profile(req: Request) -> Option<impl Responder> {
     Some(format!("<b>{}</b>", user))
 }
 
-fn login(_req: Request) -> impl Responder {
+fn login(_req: Request) -> Response {
     unimplemented!()
 }
 
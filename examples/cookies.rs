@@ -9,7 +9,7 @@ routes! {
 fn show(req: Request) -> impl Responder {
     let count: usize = req.cookie("count").unwrap_or("0").parse().unwrap();
     let new_count = count + 1;
-    Response::from_cookie("count", &new_count.to_string()).with_body(format!(
+    Response::from_cookie("count", new_count.to_string()).with_body(format!(
         r#"
 <h1> Count: {} </h1>
 <p><a href="/clear">Clear Count</a></p>
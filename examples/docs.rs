@@ -11,7 +11,7 @@ fn main() {
 
     thread::spawn(|| {
         let mut child = std::process::Command::new("watch")
-            .args(&["make", "docs"])
+            .args(["make", "docs"])
             .spawn()
             .unwrap();
         child.wait().unwrap();
@@ -21,7 +21,7 @@ fn action_with_error(_req: Request) -> vial::Error {
 }
 
 fn action_with_io_error(_req: Request) -> Result<String, io::Error> {
-    Err(io::Error::new(io::ErrorKind::Other, "A bad io:Error!"))
+    Err(io::Error::other("A bad io:Error!"))
 }
 
 fn main() {
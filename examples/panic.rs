@@ -15,11 +15,11 @@ routes! {
     GET "/custom" => custom;
 }
 
-fn boom(_req: Request) -> impl Responder {
+fn boom(_req: Request) -> Response {
     panic!("boom!")
 }
 
-fn custom(req: Request) -> impl Responder {
+fn custom(req: Request) -> Response {
     panic!("{}", req.query("msg").unwrap_or("custom"))
 }
 
@@ -52,7 +52,7 @@ fn from_io_error(_req: Request) -> Result<String, io::Error> {
     fs::read_to_string("made-up-file")
 }
 
-fn from_panic(_req: Request) -> impl Responder {
+fn from_panic(_req: Request) -> Response {
     panic!("ka-boom")
 }
 
@@ -70,7 +70,7 @@ use {
 };
 
 /// Produce an etag for an asset.
-pub fn etag(path: &str) -> Cow<str> {
+pub fn etag(path: &str) -> Cow<'_, str> {
     if is_bundled() {
         Cow::from(crate::BUILD_DATE)
     } else {
@@ -118,7 +118,7 @@ pub fn is_bundled() -> bool {
 
 /// Access to read-only, in-memory assets in bundle mode.
 fn bundled_assets() -> Option<&'static HashMap<String, &'static [u8]>> {
-    unsafe { crate::BUNDLED_ASSETS.as_ref() }
+    unsafe { (*std::ptr::addr_of!(crate::BUNDLED_ASSETS)).as_ref() }
 }
 
 /// Size of an asset in `asset_dir()`. `0` if the asset doesn't exist.
@@ -146,7 +146,7 @@ pub fn size(path: &str) -> usize {
 
 /// The directory of the asset dir.
 fn asset_dir() -> Option<&'static String> {
-    unsafe { crate::ASSET_DIR.as_ref() }
+    unsafe { (*std::ptr::addr_of!(crate::ASSET_DIR)).as_ref() }
 }
 
 /// Does the asset exist on disk? `path` is the path relative to
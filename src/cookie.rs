@@ -0,0 +1,193 @@
+//! Cookies sent to the client with a `Response`, along with the
+//! attributes that tell the browser how to treat them.
+//!
+//! `Response::with_cookie()` takes anything that converts into a
+//! [`Cookie`](struct.Cookie.html), so a plain string works when you
+//! don't care about attributes:
+//!
+//! ```rust
+//! use {std::time::Duration, vial::{Cookie, Response, SameSite}};
+//!
+//! let res = Response::new()
+//!     .with_cookie("theme", "dark")
+//!     .with_cookie(
+//!         "token",
+//!         Cookie::new("abc123")
+//!             .path("/")
+//!             .max_age(Duration::from_secs(3600))
+//!             .secure(true)
+//!             .http_only(true)
+//!             .same_site(SameSite::Strict),
+//!     );
+//! assert_eq!(Some("abc123"), res.cookie("token"));
+//! ```
+use {
+    crate::util,
+    std::{fmt, time::Duration},
+};
+
+/// The `SameSite` cookie attribute, which controls whether the
+/// browser sends a cookie along with requests from other sites.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SameSite {
+    /// Only send the cookie with requests from this site.
+    Strict,
+    /// Also send the cookie when following a link to this site.
+    Lax,
+    /// Always send the cookie. Browsers require `Secure` too.
+    None,
+}
+
+impl fmt::Display for SameSite {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                SameSite::Strict => "Strict",
+                SameSite::Lax => "Lax",
+                SameSite::None => "None",
+            }
+        )
+    }
+}
+
+/// Strip characters that would end an attribute early or break the
+/// `Set-Cookie` header.
+fn attribute(s: &str) -> String {
+    s.chars().filter(|c| *c != ';' && !c.is_control()).collect()
+}
+
+/// Cookie names are tokens, like header names. Anything else could
+/// end the `Set-Cookie` header early and start a new one.
+pub(crate) fn is_valid_name(name: &str) -> bool {
+    !name.is_empty() && name.bytes().all(util::is_token_char)
+}
+
+/// A cookie's value and attributes. The name is given when it's set
+/// on a `Response`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Cookie {
+    value: String,
+    path: Option<String>,
+    domain: Option<String>,
+    max_age: Option<Duration>,
+    secure: bool,
+    http_only: bool,
+    same_site: Option<SameSite>,
+}
+
+impl Cookie {
+    /// Create a cookie with the given value and no attributes.
+    pub fn new<S: AsRef<str>>(value: S) -> Cookie {
+        Cookie {
+            value: value.as_ref().to_string(),
+            ..Cookie::default()
+        }
+    }
+
+    /// The cookie's value, before it's percent-encoded.
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+
+    /// Only send the cookie for URLs under `path`.
+    pub fn path<S: AsRef<str>>(mut self, path: S) -> Cookie {
+        self.path = Some(attribute(path.as_ref()));
+        self
+    }
+
+    /// Send the cookie to `domain` and its subdomains.
+    pub fn domain<S: AsRef<str>>(mut self, domain: S) -> Cookie {
+        self.domain = Some(attribute(domain.as_ref()));
+        self
+    }
+
+    /// Throw the cookie away after `max_age`. Without it, the cookie
+    /// lasts until the browser is closed.
+    pub fn max_age(mut self, max_age: Duration) -> Cookie {
+        self.max_age = Some(max_age);
+        self
+    }
+
+    /// Only send the cookie over HTTPS.
+    pub fn secure(mut self, secure: bool) -> Cookie {
+        self.secure = secure;
+        self
+    }
+
+    /// Hide the cookie from JavaScript.
+    pub fn http_only(mut self, http_only: bool) -> Cookie {
+        self.http_only = http_only;
+        self
+    }
+
+    /// Set the `SameSite` attribute.
+    pub fn same_site(mut self, same_site: SameSite) -> Cookie {
+        self.same_site = Some(same_site);
+        self
+    }
+
+    /// Same cookie with a different value, keeping the attributes.
+    pub fn with_value<S: AsRef<str>>(mut self, value: S) -> Cookie {
+        self.value = value.as_ref().to_string();
+        self
+    }
+
+    /// Is this an instruction to remove the cookie?
+    pub fn is_removal(&self) -> bool {
+        self.value.is_empty()
+    }
+
+    /// `Set-Cookie` header value for this cookie. Empty cookies are
+    /// removed by expiring them in the past. `name` isn't checked
+    /// here - `Response::set_cookie()` refuses names that aren't
+    /// valid.
+    pub fn to_header(&self, name: &str) -> String {
+        let mut out = format!("{}={}", name, util::percent_encode(&self.value));
+        if self.is_removal() {
+            out.push_str("; Expires=Thu, 01 Jan 1970 00:00:00 GMT");
+        } else if let Some(max_age) = self.max_age {
+            out.push_str(&format!("; Max-Age={}", max_age.as_secs()));
+        }
+        if let Some(domain) = &self.domain {
+            out.push_str("; Domain=");
+            out.push_str(domain);
+        }
+        if let Some(path) = &self.path {
+            out.push_str("; Path=");
+            out.push_str(path);
+        }
+        if self.secure {
+            out.push_str("; Secure");
+        }
+        if self.http_only {
+            out.push_str("; HttpOnly");
+        }
+        if let Some(same_site) = self.same_site {
+            out.push_str(&format!("; SameSite={}", same_site));
+        }
+        out
+    }
+}
+
+impl From<&str> for Cookie {
+    fn from(value: &str) -> Cookie {
+        Cookie::new(value)
+    }
+}
+
+impl From<&String> for Cookie {
+    fn from(value: &String) -> Cookie {
+        Cookie::new(value)
+    }
+}
+
+impl From<String> for Cookie {
+    fn from(value: String) -> Cookie {
+        Cookie {
+            value,
+            ..Cookie::default()
+        }
+    }
+}
@@ -0,0 +1,227 @@
+//! Cross-Origin Resource Sharing, for when a web page on another
+//! origin needs to call your app from JavaScript.
+//!
+//! Describe which origins, methods, and headers are allowed with a
+//! [`Cors`](struct.Cors.html) and pass it to
+//! [`enable()`](fn.enable.html) before starting your app:
+//!
+//! ```no_run
+//! use {std::time::Duration, vial::cors::{self, Cors}};
+//!
+//! vial::routes! {
+//!     GET "/api/hello" => |_| "Hello, world!";
+//! }
+//!
+//! fn main() {
+//!     cors::enable(
+//!         Cors::new()
+//!             .allow_origin("https://app.example.com")
+//!             .allow_header("Content-Type")
+//!             .allow_credentials(true)
+//!             .max_age(Duration::from_secs(600)),
+//!     );
+//!     vial::run!().unwrap();
+//! }
+//! ```
+//!
+//! Preflight `OPTIONS` requests are answered automatically, before
+//! your routes are checked, and every other response to an allowed
+//! origin gets the right `Access-Control-*` headers.
+use {
+    crate::{Request, Response},
+    std::{
+        sync::{Arc, RwLock},
+        time::Duration,
+    },
+};
+
+/// The app's CORS settings, if enabled.
+static CORS: RwLock<Option<Arc<Cors>>> = RwLock::new(None);
+
+/// Answer preflight requests and add CORS headers to responses using
+/// these settings. Should be called before your app starts.
+pub fn enable(cors: Cors) {
+    *CORS.write().unwrap() = Some(Arc::new(cors));
+}
+
+/// Stop handling CORS.
+pub fn disable() {
+    *CORS.write().unwrap() = None;
+}
+
+/// The app's CORS settings, if enabled.
+pub(crate) fn current() -> Option<Arc<Cors>> {
+    CORS.read().unwrap().clone()
+}
+
+/// Which cross-origin requests are allowed. Nothing is allowed until
+/// you add at least one origin.
+#[derive(Debug, Clone)]
+pub struct Cors {
+    origins: Vec<String>,
+    methods: Vec<String>,
+    headers: Vec<String>,
+    any_header: bool,
+    expose_headers: Vec<String>,
+    credentials: bool,
+    max_age: Option<Duration>,
+}
+
+impl Default for Cors {
+    fn default() -> Cors {
+        Cors {
+            origins: vec![],
+            methods: ["GET", "HEAD", "POST", "PUT", "PATCH", "DELETE"]
+                .iter()
+                .map(|m| m.to_string())
+                .collect(),
+            headers: vec![],
+            any_header: false,
+            expose_headers: vec![],
+            credentials: false,
+            max_age: None,
+        }
+    }
+}
+
+impl Cors {
+    /// New settings that allow `GET`, `HEAD`, `POST`, `PUT`, `PATCH`,
+    /// and `DELETE` but no origins yet.
+    pub fn new() -> Cors {
+        Cors::default()
+    }
+
+    /// Allow requests from `origin`, like `"https://example.com"`.
+    /// Use `"*"` to allow any origin.
+    pub fn allow_origin(mut self, origin: &str) -> Cors {
+        self.origins.push(origin.trim_end_matches('/').to_string());
+        self
+    }
+
+    /// Allow requests from any origin.
+    pub fn allow_any_origin(self) -> Cors {
+        self.allow_origin("*")
+    }
+
+    /// Replace the allowed methods.
+    pub fn allow_methods(mut self, methods: &[&str]) -> Cors {
+        self.methods = methods.iter().map(|m| m.to_uppercase()).collect();
+        self
+    }
+
+    /// Allow a request header, like `"Content-Type"` or
+    /// `"Authorization"`.
+    pub fn allow_header(mut self, header: &str) -> Cors {
+        self.headers.push(header.to_string());
+        self
+    }
+
+    /// Allow whatever headers the preflight request asks for.
+    pub fn allow_any_header(mut self) -> Cors {
+        self.any_header = true;
+        self
+    }
+
+    /// Let JavaScript read a response header it otherwise couldn't.
+    pub fn expose_header(mut self, header: &str) -> Cors {
+        self.expose_headers.push(header.to_string());
+        self
+    }
+
+    /// Allow cookies and `Authorization` headers to be sent from the
+    /// origins added with [`allow_origin()`](#method.allow_origin).
+    /// Their origin is sent back instead of `*`, since browsers don't
+    /// allow both. Origins only allowed by `"*"` never get
+    /// credentials - list them by name if they need to.
+    pub fn allow_credentials(mut self, credentials: bool) -> Cors {
+        self.credentials = credentials;
+        self
+    }
+
+    /// How long browsers can cache the answer to a preflight request.
+    pub fn max_age(mut self, max_age: Duration) -> Cors {
+        self.max_age = Some(max_age);
+        self
+    }
+
+    /// Is `origin` allowed?
+    pub fn allows_origin(&self, origin: &str) -> bool {
+        self.origins.iter().any(|o| o == "*" || o == origin)
+    }
+
+    /// Can `origin` send credentials? Only if it was allowed by name,
+    /// so `"*"` can't be used to hand them to every site.
+    fn allows_credentials(&self, origin: &str) -> bool {
+        self.credentials && origin != "*" && self.origins.iter().any(|o| o == origin)
+    }
+
+    /// The `Access-Control-Allow-Origin` value for an allowed origin.
+    fn allow_origin_value<'o>(&self, origin: &'o str) -> &'o str {
+        if self.allows_credentials(origin) || !self.origins.iter().any(|o| o == "*") {
+            origin
+        } else {
+            "*"
+        }
+    }
+
+    /// Answer a preflight request, or return `None` if `req` isn't
+    /// one. Preflights from origins that aren't allowed get a `403`.
+    pub(crate) fn preflight(&self, req: &Request) -> Option<Response> {
+        if req.method() != "OPTIONS" {
+            return None;
+        }
+        let origin = req.header("Origin")?;
+        let method = req.header("Access-Control-Request-Method")?;
+        if !self.allows_origin(&origin) || !self.methods.iter().any(|m| *m == *method) {
+            return Some(Response::from(403));
+        }
+
+        let mut res = Response::from(204).with_header("Vary", "Origin");
+        res.set_header(
+            "Access-Control-Allow-Origin",
+            self.allow_origin_value(&origin),
+        );
+        res.set_header("Access-Control-Allow-Methods", &self.methods.join(", "));
+        if self.any_header {
+            if let Some(headers) = req.header("Access-Control-Request-Headers") {
+                res.set_header("Access-Control-Allow-Headers", &headers);
+            }
+        } else if !self.headers.is_empty() {
+            res.set_header("Access-Control-Allow-Headers", &self.headers.join(", "));
+        }
+        if self.allows_credentials(&origin) {
+            res.set_header("Access-Control-Allow-Credentials", "true");
+        }
+        if let Some(max_age) = self.max_age {
+            res.set_header("Access-Control-Max-Age", &max_age.as_secs().to_string());
+        }
+        Some(res)
+    }
+
+    /// Add CORS headers to the response for a request from `origin`.
+    pub(crate) fn decorate(&self, origin: &str, res: &mut Response) {
+        if !self.allows_origin(origin) {
+            return;
+        }
+        res.set_header(
+            "Access-Control-Allow-Origin",
+            self.allow_origin_value(origin),
+        );
+        let varies = res
+            .header_all("Vary")
+            .iter()
+            .any(|vary| vary.to_lowercase().contains("origin"));
+        if !varies {
+            res.append_header("Vary", "Origin");
+        }
+        if self.allows_credentials(origin) {
+            res.set_header("Access-Control-Allow-Credentials", "true");
+        }
+        if !self.expose_headers.is_empty() {
+            res.set_header(
+                "Access-Control-Expose-Headers",
+                &self.expose_headers.join(", "),
+            );
+        }
+    }
+}
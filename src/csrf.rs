@@ -0,0 +1,90 @@
+//! Protection against cross-site request forgery, where another site
+//! tricks someone's browser into submitting a form to your app.
+//!
+//! Enable the `csrf` feature (which also turns on `sessions`) and add
+//! [`protect`](fn.protect.html) as a filter. Every `POST`, `PUT`,
+//! `PATCH`, and `DELETE` request will then need to include the
+//! session's CSRF token, either as a `csrf_token` form field or in an
+//! `X-CSRF-Token` header, or it gets a `403 Forbidden`:
+//!
+//! ```no_run
+//! use vial::{csrf::protect, prelude::*};
+//!
+//! routes! {
+//!     #![filter(protect)]
+//!
+//!     GET "/" => |req| format!(
+//!         "<form method='POST' action='/echo'>
+//!             {}
+//!             <input type='text' name='echo'/>
+//!             <input type='submit'/>
+//!         </form>",
+//!         req.csrf_field()
+//!     );
+//!     POST "/echo" => |req| req.form("echo").unwrap_or("?").to_string();
+//! }
+//!
+//! fn main() {
+//!     vial::run!().unwrap();
+//! }
+//! ```
+//!
+//! The token is created the first time
+//! [`Request::csrf_token()`](../struct.Request.html#method.csrf_token)
+//! is called and stored in the session, so it stays the same until
+//! the session is cleared.
+//!
+//! Routes that need to accept posts from other sites, like webhooks,
+//! can opt out with `#[skip(protect)]`:
+//!
+//! ```no_run
+//! use vial::{csrf::protect, prelude::*};
+//!
+//! routes! {
+//!     #![filter(protect)]
+//!
+//!     #[skip(protect)]
+//!     POST "/hooks/github" => |_| "Thanks!";
+//! }
+//! # fn main() {}
+//! ```
+use crate::{util, Request, Response};
+
+/// Session key the token is stored under.
+pub const SESSION_KEY: &str = "_csrf_token";
+
+/// Form field checked for the token.
+pub const FORM_FIELD: &str = "csrf_token";
+
+/// Header checked for the token, for JavaScript requests.
+pub const HEADER: &str = "X-CSRF-Token";
+
+/// A new, random token.
+pub(crate) fn new_token() -> String {
+    let mut bytes = [0u8; 32];
+    getrandom::getrandom(&mut bytes).expect("Can't generate CSRF token");
+    util::base64_url(&bytes)
+}
+
+/// Filter that rejects `POST`, `PUT`, `PATCH`, and `DELETE` requests
+/// without a valid CSRF token with `403 Forbidden`.
+pub fn protect(req: &mut Request) -> Option<Response> {
+    if !matches!(req.method(), "POST" | "PUT" | "PATCH" | "DELETE") {
+        return None;
+    }
+
+    let expected = req.session(SESSION_KEY).unwrap_or_default();
+    let sent = match req.form(FORM_FIELD) {
+        Some(token) => token.to_string(),
+        None => req
+            .header(HEADER)
+            .map(|h| h.into_owned())
+            .unwrap_or_default(),
+    };
+
+    if !expected.is_empty() && util::constant_time_eq(expected.as_bytes(), sent.as_bytes()) {
+        None
+    } else {
+        Some(Response::from(403).with_body("403 Forbidden: Invalid CSRF Token"))
+    }
+}
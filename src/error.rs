@@ -19,19 +19,80 @@ pub enum Error {
     ParseHeaderValue,
     /// Failed to parse HTTP request.
     ParseError,
+    /// Header name with characters that aren't allowed in one. Only
+    /// checked in strict mode, or when setting a response header.
+    InvalidHeaderName,
+    /// Header value with control characters in it. Only checked in
+    /// strict mode, or when setting a response header.
+    InvalidHeaderValue,
+    /// More headers than the limit allows. Only checked in strict mode.
+    TooManyHeaders,
+    /// `Content-Length` that isn't a number. Only checked in strict
+    /// mode.
+    InvalidContentLength,
+    /// More than one `Content-Length` header. Only checked in strict
+    /// mode.
+    ConflictingContentLength,
+    /// Both `Content-Length` and `Transfer-Encoding` headers. Only
+    /// checked in strict mode.
+    ContentLengthWithTransferEncoding,
+    /// Client took too long to send its request.
+    RequestTimeout,
+    /// Missing or malformed PROXY protocol header.
+    ProxyProtocol,
     /// io::Error
     IO(io::Error),
     /// Unknown error.
     Other(String),
 
+    #[cfg(feature = "cookies")]
+    /// Cookie name with characters that aren't allowed in one.
+    InvalidCookieName,
+
     #[cfg(feature = "sessions")]
-    /// Failed to decode raw session value.
+    /// Failed to decode raw session value, or it's been tampered with.
     SessionDecode,
+
+    #[cfg(feature = "sessions")]
+    /// Session value is past its expiration time.
+    SessionExpired,
+
+    #[cfg(feature = "sessions")]
+    /// Session is too big to fit in a cookie. Contains the size, in bytes.
+    SessionTooLarge(usize),
+
+    #[cfg(feature = "websocket")]
+    /// The client broke the WebSocket protocol. The connection has
+    /// been closed.
+    WebSocket(String),
+}
+
+impl Error {
+    /// Status code to answer a request that failed with this error,
+    /// if it's the client's fault.
+    pub(crate) fn status(&self) -> Option<usize> {
+        use Error::*;
+        match self {
+            TooManyHeaders => Some(431),
+            UnknownHTTPMethod(..)
+            | ParseVersion
+            | ExpectedCRLF
+            | ParseHeaderName
+            | ParseHeaderValue
+            | ParseError
+            | InvalidHeaderName
+            | InvalidHeaderValue
+            | InvalidContentLength
+            | ConflictingContentLength
+            | ContentLengthWithTransferEncoding => Some(400),
+            _ => None,
+        }
+    }
 }
 
 impl From<Error> for io::Error {
     fn from(err: Error) -> io::Error {
-        io::Error::new(io::ErrorKind::Other, err.to_string())
+        io::Error::other(err.to_string())
     }
 }
 
@@ -63,12 +124,30 @@ impl fmt::Display for Error {
                 Error::ParseHeaderName => "Error Parsing HTTP Header name",
                 Error::ParseHeaderValue => "Error Parsing HTTP Header value",
                 Error::ParseError => "Error Parsing HTTP Request",
+                Error::InvalidHeaderName => "Invalid HTTP Header Name",
+                Error::InvalidHeaderValue => "Invalid HTTP Header Value",
+                Error::TooManyHeaders => "Too Many HTTP Headers",
+                Error::InvalidContentLength => "Invalid Content-Length",
+                Error::ConflictingContentLength => "More Than One Content-Length",
+                Error::ContentLengthWithTransferEncoding => {
+                    "Both Content-Length And Transfer-Encoding"
+                }
+                Error::RequestTimeout => "Request Timed Out",
+                Error::ProxyProtocol => "Invalid PROXY Protocol Header",
                 Error::AssetNotFound(..) => "Can't Find Asset",
                 Error::IO(..) => "io::Error While Parsing HTTP Request",
                 Error::Other(reason) => reason,
 
+                #[cfg(feature = "cookies")]
+                Error::InvalidCookieName => "Invalid Cookie Name",
                 #[cfg(feature = "sessions")]
                 Error::SessionDecode => "Error Decoding Session",
+                #[cfg(feature = "sessions")]
+                Error::SessionExpired => "Session Expired",
+                #[cfg(feature = "sessions")]
+                Error::SessionTooLarge(..) => "Session Too Large For Cookie",
+                #[cfg(feature = "websocket")]
+                Error::WebSocket(reason) => reason,
             }
         )
     }
@@ -97,9 +176,33 @@ impl PartialEq for Error {
             ParseHeaderName => matches!(other, ParseHeaderName),
             ParseHeaderValue => matches!(other, ParseHeaderValue),
             ParseError => matches!(other, ParseError),
+            InvalidHeaderName => matches!(other, InvalidHeaderName),
+            InvalidHeaderValue => matches!(other, InvalidHeaderValue),
+            TooManyHeaders => matches!(other, TooManyHeaders),
+            InvalidContentLength => matches!(other, InvalidContentLength),
+            ConflictingContentLength => matches!(other, ConflictingContentLength),
+            ContentLengthWithTransferEncoding => {
+                matches!(other, ContentLengthWithTransferEncoding)
+            }
+            RequestTimeout => matches!(other, RequestTimeout),
+            ProxyProtocol => matches!(other, ProxyProtocol),
 
+            #[cfg(feature = "cookies")]
+            InvalidCookieName => matches!(other, InvalidCookieName),
             #[cfg(feature = "sessions")]
             SessionDecode => matches!(other, SessionDecode),
+            #[cfg(feature = "sessions")]
+            SessionExpired => matches!(other, SessionExpired),
+            #[cfg(feature = "sessions")]
+            SessionTooLarge(s) => match other {
+                SessionTooLarge(o) => s == o,
+                _ => false,
+            },
+            #[cfg(feature = "websocket")]
+            WebSocket(s) => match other {
+                WebSocket(o) => s == o,
+                _ => false,
+            },
         }
     }
 }
@@ -0,0 +1,218 @@
+use crate::{util, Error, Result};
+
+/// A parsed `Content-Type` header, like `text/html; charset=utf-8`.
+///
+/// Returned by [`Request::content_type()`](struct.Request.html#method.content_type).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContentType<'r> {
+    mime: &'r str,
+    params: Vec<(&'r str, &'r str)>,
+}
+
+impl<'r> ContentType<'r> {
+    /// Parse a `Content-Type` value. Returns `None` if there's no
+    /// `type/subtype` to be found.
+    pub fn parse(value: &'r str) -> Option<ContentType<'r>> {
+        let mut parts = value.split(';');
+        let mime = parts.next()?.trim();
+        if !mime.contains('/') {
+            return None;
+        }
+        let params = parts
+            .filter_map(|param| {
+                let (name, value) = param.split_once('=')?;
+                let value = value.trim();
+                let value = value
+                    .strip_prefix('"')
+                    .and_then(|v| v.strip_suffix('"'))
+                    .unwrap_or(value);
+                Some((name.trim(), value))
+            })
+            .collect();
+        Some(ContentType { mime, params })
+    }
+
+    /// The `type/subtype`, without any parameters.
+    pub fn mime(&self) -> &'r str {
+        self.mime
+    }
+
+    /// Is this the given `type/subtype`? Case insensitive.
+    pub fn is(&self, mime: &str) -> bool {
+        self.mime.eq_ignore_ascii_case(mime)
+    }
+
+    /// Value of a parameter, like `boundary`. `name` is case
+    /// insensitive and quotes around the value are removed.
+    pub fn param(&self, name: &str) -> Option<&'r str> {
+        self.params
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| *v)
+    }
+
+    /// The `charset` parameter, if one was sent.
+    pub fn charset(&self) -> Option<&'r str> {
+        self.param("charset")
+    }
+}
+
+/// A parsed `Authorization` header: a scheme followed by credentials,
+/// like `Bearer abc123`.
+///
+/// Returned by [`Request::authorization()`](struct.Request.html#method.authorization).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Authorization<'r> {
+    scheme: &'r str,
+    credentials: &'r str,
+}
+
+impl<'r> Authorization<'r> {
+    /// Parse an `Authorization` value. Returns `None` if it's empty.
+    pub fn parse(value: &'r str) -> Option<Authorization<'r>> {
+        let value = value.trim();
+        if value.is_empty() {
+            return None;
+        }
+        let (scheme, credentials) = value.split_once(' ').unwrap_or((value, ""));
+        Some(Authorization {
+            scheme,
+            credentials: credentials.trim(),
+        })
+    }
+
+    /// Authentication scheme as sent, like `Basic` or `Bearer`.
+    pub fn scheme(&self) -> &'r str {
+        self.scheme
+    }
+
+    /// Everything after the scheme.
+    pub fn credentials(&self) -> &'r str {
+        self.credentials
+    }
+
+    /// The token, if this is `Bearer` authorization.
+    pub fn bearer(&self) -> Option<&'r str> {
+        if self.scheme.eq_ignore_ascii_case("bearer") && !self.credentials.is_empty() {
+            Some(self.credentials)
+        } else {
+            None
+        }
+    }
+
+    /// The decoded `(username, password)`, if this is `Basic`
+    /// authorization.
+    pub fn basic(&self) -> Option<(String, String)> {
+        if !self.scheme.eq_ignore_ascii_case("basic") {
+            return None;
+        }
+        let decoded = String::from_utf8(util::base64_decode(self.credentials)?).ok()?;
+        let (user, pass) = decoded.split_once(':')?;
+        Some((user.to_string(), pass.to_string()))
+    }
+}
+
+/// Headers sent with a [`Response`](struct.Response.html). Names are
+/// case insensitive and keep the casing they were first set with.
+/// Headers are written in the order they were added, and a name can
+/// appear more than once - like `Link`, `Vary`, or `WWW-Authenticate`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HeaderMap {
+    entries: Vec<(String, String)>,
+}
+
+impl HeaderMap {
+    /// Create an empty map.
+    pub fn new() -> HeaderMap {
+        HeaderMap::default()
+    }
+
+    /// Number of headers, counting repeats.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Are there no headers at all?
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Is there at least one header named `name`?
+    pub fn contains(&self, name: &str) -> bool {
+        self.get(name).is_some()
+    }
+
+    /// First value for `name`.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.position(name).map(|i| self.entries[i].1.as_ref())
+    }
+
+    /// Every value for `name`, in order.
+    pub fn get_all(&self, name: &str) -> Vec<&str> {
+        self.entries
+            .iter()
+            .filter(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_ref())
+            .collect()
+    }
+
+    /// All headers as `(name, value)` pairs, in order.
+    pub fn iter(&self) -> impl ExactSizeIterator<Item = (&str, &str)> {
+        self.entries.iter().map(|(n, v)| (n.as_ref(), v.as_ref()))
+    }
+
+    /// Set a header, replacing every existing value for `name`. The
+    /// header keeps its place if it was already set.
+    pub fn set(&mut self, name: &str, value: &str) -> Result<()> {
+        check(name, value)?;
+        match self.position(name) {
+            Some(i) => {
+                self.entries[i].1 = value.to_string();
+                let mut i = i + 1;
+                while i < self.entries.len() {
+                    if self.entries[i].0.eq_ignore_ascii_case(name) {
+                        self.entries.remove(i);
+                    } else {
+                        i += 1;
+                    }
+                }
+            }
+            None => self.entries.push((name.to_string(), value.to_string())),
+        }
+        Ok(())
+    }
+
+    /// Add a header, keeping any existing values for `name`.
+    pub fn append(&mut self, name: &str, value: &str) -> Result<()> {
+        check(name, value)?;
+        self.entries.push((name.to_string(), value.to_string()));
+        Ok(())
+    }
+
+    /// Remove every value for `name`, returning whether there were
+    /// any.
+    pub fn remove(&mut self, name: &str) -> bool {
+        let len = self.entries.len();
+        self.entries.retain(|(n, _)| !n.eq_ignore_ascii_case(name));
+        len != self.entries.len()
+    }
+
+    fn position(&self, name: &str) -> Option<usize> {
+        self.entries
+            .iter()
+            .position(|(n, _)| n.eq_ignore_ascii_case(name))
+    }
+}
+
+/// Header names must be tokens, and values can't contain control
+/// characters other than tab - a `\r\n` would let whoever controls
+/// the value add headers of their own, or end the head early.
+fn check(name: &str, value: &str) -> Result<()> {
+    if name.is_empty() || !name.bytes().all(util::is_token_char) {
+        Err(Error::InvalidHeaderName)
+    } else if value.bytes().any(|b| b.is_ascii_control() && b != b'\t') {
+        Err(Error::InvalidHeaderValue)
+    } else {
+        Ok(())
+    }
+}
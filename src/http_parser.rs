@@ -1,4 +1,4 @@
-use crate::{request::Span, Error, Request};
+use crate::{request::Span, util, Error, Request, Version};
 
 /// Status of parsing an HTTP request. The request may have been only
 /// partial, in which case the buffer is returned in `Partial` so we
@@ -13,7 +13,25 @@ pub enum Status {
 const MAX_HEADER_SIZE: usize = 8192;
 
 /// Parse a raw HTTP request into a Request struct.
-pub fn parse(mut buffer: Vec<u8>) -> Result<Status, Error> {
+pub fn parse(buffer: Vec<u8>) -> Result<Status, Error> {
+    parse_with(buffer, None)
+}
+
+/// Parse a raw HTTP request into a Request struct, rejecting anything
+/// that different servers and proxies might read differently, which
+/// could let a request be smuggled past a proxy:
+///
+/// - More than one `Content-Length`, or one that isn't a number.
+/// - `Content-Length` and `Transfer-Encoding` together.
+/// - Header names that aren't valid tokens.
+/// - Control characters in header values.
+/// - More than `max_headers` headers.
+pub fn parse_strict(buffer: Vec<u8>, max_headers: usize) -> Result<Status, Error> {
+    parse_with(buffer, Some(max_headers))
+}
+
+/// Parse a raw HTTP request, strictly if given a header limit.
+fn parse_with(mut buffer: Vec<u8>, strict: Option<usize>) -> Result<Status, Error> {
     let mut pos = 0;
 
     // clear preceding \n or \r
@@ -45,13 +63,7 @@ pub fn parse(mut buffer: Vec<u8>) -> Result<Status, Error> {
                 b"PATCH" | b"TRACE" => 5,
                 _ => 0,
             },
-            b"DEL" => {
-                if &buffer[0..6] == b"DELETE" {
-                    6
-                } else {
-                    0
-                }
-            }
+            b"DEL" if &buffer[0..6] == b"DELETE" => 6,
             b"CON" | b"OPT" => match &buffer[0..7] {
                 b"CONNECT" | b"OPTIONS" => 7,
                 _ => 0,
@@ -77,7 +89,7 @@ pub fn parse(mut buffer: Vec<u8>) -> Result<Status, Error> {
     pos = method_len + 1 + path_len + 1;
 
     // Parse version: GET / HTTP/1.1
-    for c in b"HTTP/1.1" {
+    for c in b"HTTP/1." {
         if buffer.len() <= pos {
             return Ok(Status::Partial(buffer));
         } else if buffer[pos] == *c {
@@ -86,6 +98,17 @@ pub fn parse(mut buffer: Vec<u8>) -> Result<Status, Error> {
             return Err(Error::ParseVersion);
         }
     }
+    need!(1);
+    let version = match buffer[pos] {
+        b'0' => Version::HTTP10,
+        b'1' => Version::HTTP11,
+        _ => return Err(Error::ParseVersion),
+    };
+    pos += 1;
+
+    // Absolute-form: GET http://example.com/ HTTP/1.1
+    let target = Span(method_len + 1, method_len + 1 + path_len);
+    let (path, authority) = split_target(&mut buffer, target);
 
     // Parse first line break
     if need!(1) && buffer[pos] == b'\n' {
@@ -99,15 +122,9 @@ pub fn parse(mut buffer: Vec<u8>) -> Result<Status, Error> {
     // End here if there are no headers.
     if (need!(1) && buffer[pos] == b'\n') || (need!(2) && &buffer[pos..pos + 2] == b"\r\n") {
         let method = Span(0, method_len);
-        let path = Span(method_len + 1, method_len + 1 + path_len);
-
-        return Ok(Status::Complete(Request::new(
-            method,
-            path,
-            Vec::new(),
-            Span::new(),
-            buffer,
-        )));
+        let mut req = Request::new(method, path, Vec::new(), Span::new(), buffer);
+        req.set_target(version, authority);
+        return Ok(Status::Complete(req));
     }
 
     // Parse headers
@@ -116,7 +133,7 @@ pub fn parse(mut buffer: Vec<u8>) -> Result<Status, Error> {
     let mut name = Span::new();
     let mut saw_end = false;
     let mut parsing_key = true;
-    let mut content_length = 0;
+    let mut content_length = None;
     let mut len = 0; // header length
 
     while let Some(c) = buffer.get(pos) {
@@ -141,8 +158,11 @@ pub fn parse(mut buffer: Vec<u8>) -> Result<Status, Error> {
 
             let value = Span(start, pos);
             headers.push((name, value));
-            if name.in_buf(&buffer).to_ascii_lowercase() == "content-length" {
-                content_length = value.in_buf(&buffer).parse().unwrap_or(0);
+            if name.in_buf(&buffer).eq_ignore_ascii_case("content-length") {
+                // strict mode turns away repeats; otherwise the first
+                // wins, like `Request::content_length()`
+                let size = parse_content_length(&buffer[value.0..value.1])?;
+                content_length = content_length.or(Some(size));
             }
 
             name = Span::new();
@@ -185,15 +205,120 @@ pub fn parse(mut buffer: Vec<u8>) -> Result<Status, Error> {
         return Ok(Status::Partial(buffer));
     }
 
+    if let Some(max_headers) = strict {
+        check_headers(&buffer, &headers, max_headers)?;
+    }
+
     let method = Span(0, method_len);
-    let path = Span(method_len + 1, method_len + 1 + path_len);
-    let body = if content_length > 0 {
-        Span(pos, pos + buffer.len())
+    let body = match content_length {
+        Some(size) if size > 0 => Span(
+            pos,
+            pos.checked_add(size).ok_or(Error::InvalidContentLength)?,
+        ),
+        _ => Span::new(),
+    };
+
+    let mut req = Request::new(method, path, headers, body, buffer);
+    req.set_target(version, authority);
+    Ok(Status::Complete(req))
+}
+
+/// Check headers for anything strict mode rejects.
+fn check_headers(buffer: &[u8], headers: &[(Span, Span)], max_headers: usize) -> Result<(), Error> {
+    if headers.len() > max_headers {
+        return Err(Error::TooManyHeaders);
+    }
+
+    let mut content_length = false;
+    let mut transfer_encoding = false;
+    for (name, value) in headers {
+        let name = &buffer[name.0..name.1];
+        let value = &buffer[value.0..value.1];
+        if name.is_empty() || !name.iter().all(|b| util::is_token_char(*b)) {
+            return Err(Error::InvalidHeaderName);
+        }
+        // a \r here was on its own, not part of a line ending
+        if value.iter().any(|b| b.is_ascii_control() && *b != b'\t') {
+            return Err(Error::InvalidHeaderValue);
+        }
+
+        if name.eq_ignore_ascii_case(b"content-length") {
+            if content_length {
+                return Err(Error::ConflictingContentLength);
+            }
+            content_length = true;
+        } else if name.eq_ignore_ascii_case(b"transfer-encoding") {
+            transfer_encoding = true;
+        }
+    }
+
+    if content_length && transfer_encoding {
+        return Err(Error::ContentLengthWithTransferEncoding);
+    }
+    Ok(())
+}
+
+/// Parse a `Content-Length` value, which has to be a plain number.
+fn parse_content_length(value: &[u8]) -> Result<usize, Error> {
+    let value = trim(value);
+    if value.is_empty() || !value.iter().all(u8::is_ascii_digit) {
+        return Err(Error::InvalidContentLength);
+    }
+    std::str::from_utf8(value)
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .ok_or(Error::InvalidContentLength)
+}
+
+/// Trim spaces and tabs from both ends of a header value.
+fn trim(mut value: &[u8]) -> &[u8] {
+    while let [b' ' | b'\t', rest @ ..] = value {
+        value = rest;
+    }
+    while let [rest @ .., b' ' | b'\t'] = value {
+        value = rest;
+    }
+    value
+}
+
+/// Split an absolute-form request target, like
+/// `http://example.com/path`, into its path and authority. Other
+/// targets are all path.
+///
+/// The path has to start with `/`, so the target is rearranged in
+/// place: the authority is moved over the `http://` and, if the path
+/// is missing or only a `?query`, a `/` is written just before it.
+fn split_target(buffer: &mut [u8], target: Span) -> (Span, Span) {
+    let Span(start, end) = target;
+    let lower = buffer[start..end].to_ascii_lowercase();
+    let scheme_len = if lower.starts_with(b"http://") {
+        7
+    } else if lower.starts_with(b"https://") {
+        8
     } else {
-        Span::new()
+        return (target, Span::new());
     };
 
-    Ok(Status::Complete(Request::new(
-        method, path, headers, body, buffer,
-    )))
+    let rest = start + scheme_len;
+    let path_start = buffer[rest..end]
+        .iter()
+        .position(|c| *c == b'/' || *c == b'?')
+        .map_or(end, |i| rest + i);
+    // skip any user:password@
+    let host_start = buffer[rest..path_start]
+        .iter()
+        .rposition(|c| *c == b'@')
+        .map_or(rest, |i| rest + i + 1);
+
+    let host_len = path_start - host_start;
+    buffer.copy_within(host_start..path_start, start);
+    let authority = Span(start, start + host_len);
+
+    if buffer.get(path_start) == Some(&b'/') {
+        (Span(path_start, end), authority)
+    } else {
+        // the scheme freed up room for this
+        buffer[path_start - 1] = b'/';
+        (Span(path_start - 1, end), authority)
+    }
 }
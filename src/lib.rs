@@ -14,6 +14,22 @@
 //!   types or a few built-ins like `String` into a `Response`.
 //! - **[asset](asset/index.html)**: Serving of static files and
 //!   support for bundling into the release binary.
+//! - **[mime](mime/index.html)**: Content types for files and assets,
+//!   and adding your own.
+//! - **[test](test/index.html)**: Make requests to your routes in
+//!   tests without starting a server.
+//! - **[cors](cors/index.html)**: Cross-origin requests and
+//!   automatic preflight responses.
+//! - **[sse](sse/index.html)**: Stream Server-Sent Events to the
+//!   browser.
+//! - **[proxy](proxy/index.html)**: Find the real client behind a
+//!   reverse proxy.
+//! - **[ratelimit](ratelimit/index.html)**: Filters that limit how
+//!   often clients can make requests.
+//! - **[limits](limits/index.html)**: Timeouts and connection limits
+//!   that keep slow clients and bursts from tying up the server.
+//! - **[restart](restart/index.html)**: Deploy a new binary without
+//!   dropping connections.
 //!
 //! Everything else... well, that's up to you.
 //!
@@ -59,10 +75,12 @@
 //!
 //! - [x] **horror**: Small & fast macro-based HTML builder, via [horrowshow].
 //! - [x] **json_serde**: `Request::json` and `Response::with_json` powers,
-//!       via Serde.
+//!   via Serde.
 //! - [x] **json_nano**: `Request::json` and `Response::with_json`, via nanoserde.
 //! - [x] **cookies**: Cookie monster!
 //! - [x] **sessions**: Session support
+//! - [x] **csrf**: [Cross-site request forgery](csrf/index.html) protection
+//! - [x] **websocket**: [WebSockets](websocket/index.html)
 //! - [ ] **multipart**: Multipart form data (file uploads)
 //! - [ ] **log**: Access logging
 //!
@@ -204,14 +222,30 @@ mod macros;
 pub mod asset;
 
 mod cache;
+#[cfg(feature = "cookies")]
+mod cookie;
+pub mod cors;
+#[cfg(feature = "csrf")]
+pub mod csrf;
 mod error;
+mod header;
+pub mod limits;
+mod listener;
 mod method;
+pub mod mime;
 pub mod prelude;
+pub mod proxy;
+pub mod ratelimit;
 mod request;
 mod responder;
 mod response;
+#[cfg(unix)]
+pub mod restart;
 mod router;
 mod server;
+pub mod sse;
+pub mod test;
+mod version;
 
 // used in tests
 #[doc(hidden)]
@@ -229,14 +263,30 @@ pub mod storage;
 pub mod horrorshow;
 
 #[cfg(feature = "sessions")]
-#[doc(hidden)]
 pub mod session;
 
+#[cfg(feature = "websocket")]
+pub mod websocket;
+
 pub use {
-    bundler::bundle_assets, cache::TypeCache, error::Error, method::Method, request::Request,
-    responder::Responder, response::Response, router::Router, server::run,
+    bundler::bundle_assets,
+    cache::TypeCache,
+    error::Error,
+    header::{Authorization, ContentType, HeaderMap},
+    listener::{Bind, Listener, ListenerKind},
+    method::Method,
+    request::Request,
+    request::RequestBuilder,
+    responder::Responder,
+    response::Response,
+    router::Router,
+    server::run,
+    version::Version,
 };
 
+#[cfg(feature = "cookies")]
+pub use cookie::{Cookie, SameSite};
+
 /// Convenience Result that returns `vial::Error`.
 pub type Result<T> = std::result::Result<T, Error>;
 
@@ -0,0 +1,386 @@
+//! Limits that keep slow or stalled clients from tying up the
+//! server's threads.
+//!
+//! Every connection gets a deadline for sending its request line and
+//! headers, an idle timeout while sending its body, and a timeout for
+//! each write of the response. Clients that trickle data in or read
+//! it back slower than a minimum rate are cut off too. Requests that
+//! run out of time get a `408 Request Timeout`.
+//!
+//! When every thread is busy, new connections wait in a queue. Once
+//! the queue is full, or a single client IP has too many connections
+//! open, new connections are turned away right away with a
+//! `503 Service Unavailable` and a `Retry-After` header instead of
+//! waiting longer and longer.
+//!
+//! Request bodies over the maximum size, if one is set, get a
+//! `413 Payload Too Large` before they're read. Strict parsing turns
+//! away requests a proxy in front of the app might read differently.
+//!
+//! The defaults suit most apps. To change them, pass new
+//! [`Limits`](struct.Limits.html) to [`set()`](fn.set.html) before
+//! starting your app:
+//!
+//! ```no_run
+//! use {std::time::Duration, vial::limits::{self, Limits}};
+//!
+//! vial::routes! {
+//!     GET "/" => |_| "Hello, world!";
+//! }
+//!
+//! fn main() {
+//!     limits::set(
+//!         Limits::new()
+//!             .header_timeout(Duration::from_secs(5))
+//!             .body_timeout(Duration::from_secs(60))
+//!             .max_connections_per_ip(20),
+//!     );
+//!     vial::run!().unwrap();
+//! }
+//! ```
+use {
+    crate::{
+        http_parser::{self, Status},
+        listener::Stream,
+        Response, Result,
+    },
+    std::{
+        collections::HashMap,
+        io::{self, Read, Write},
+        net::IpAddr,
+        sync::{Arc, Mutex, RwLock},
+        time::{Duration, Instant},
+    },
+};
+
+/// How long a transfer can run before its rate is checked, so slow
+/// starts and small requests aren't penalized.
+pub const RATE_GRACE_PERIOD: Duration = Duration::from_secs(5);
+
+/// The app's limits, if changed from the defaults.
+static LIMITS: RwLock<Option<Arc<Limits>>> = RwLock::new(None);
+
+/// Use these limits for new connections. Should be called before your
+/// app starts.
+pub fn set(limits: Limits) {
+    *LIMITS.write().unwrap() = Some(Arc::new(limits));
+}
+
+/// The app's current limits.
+pub(crate) fn current() -> Arc<Limits> {
+    LIMITS
+        .read()
+        .unwrap()
+        .clone()
+        .unwrap_or_else(|| Arc::new(Limits::default()))
+}
+
+/// How long clients have to send requests and read responses, and
+/// how many connections can be waiting at once.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Limits {
+    header_timeout: Duration,
+    body_timeout: Duration,
+    write_timeout: Duration,
+    min_read_rate: u64,
+    min_write_rate: u64,
+    max_pending: usize,
+    max_per_ip: usize,
+    retry_after: Duration,
+    max_body_size: u64,
+    strict: bool,
+    max_headers: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Limits {
+        Limits {
+            header_timeout: Duration::from_secs(10),
+            body_timeout: Duration::from_secs(30),
+            write_timeout: Duration::from_secs(30),
+            min_read_rate: 240,
+            min_write_rate: 240,
+            max_pending: 100,
+            max_per_ip: 0,
+            retry_after: Duration::from_secs(5),
+            max_body_size: 0,
+            strict: false,
+            max_headers: 100,
+        }
+    }
+}
+
+impl Limits {
+    /// New limits with the defaults: 10 seconds to send the headers,
+    /// 30 seconds between reads of the body, 30 seconds for each
+    /// write, at least 240 bytes per second either way, up to 100
+    /// connections waiting for a thread, and no limit per client IP
+    /// or on the size of request bodies.
+    pub fn new() -> Limits {
+        Limits::default()
+    }
+
+    /// How long clients have, in total, to send the request line and
+    /// headers.
+    pub fn header_timeout(mut self, timeout: Duration) -> Limits {
+        self.header_timeout = timeout;
+        self
+    }
+
+    /// How long the body can go without any data arriving.
+    pub fn body_timeout(mut self, timeout: Duration) -> Limits {
+        self.body_timeout = timeout;
+        self
+    }
+
+    /// How long a single write of the response can block.
+    pub fn write_timeout(mut self, timeout: Duration) -> Limits {
+        self.write_timeout = timeout;
+        self
+    }
+
+    /// Slowest a request body can be sent, in bytes per second, once
+    /// the grace period is over. `0` turns the check off.
+    pub fn min_read_rate(mut self, bytes_per_sec: u64) -> Limits {
+        self.min_read_rate = bytes_per_sec;
+        self
+    }
+
+    /// Slowest a response can be read, in bytes per second, once the
+    /// grace period is over. `0` turns the check off.
+    pub fn min_write_rate(mut self, bytes_per_sec: u64) -> Limits {
+        self.min_write_rate = bytes_per_sec;
+        self
+    }
+
+    /// How many connections can wait for a free thread before new
+    /// ones are turned away.
+    pub fn max_pending(mut self, max: usize) -> Limits {
+        self.max_pending = max;
+        self
+    }
+
+    /// How many connections a single client IP can have open at once.
+    /// `0` means no limit, which is the default since clients behind
+    /// the same proxy or NAT share an IP.
+    pub fn max_connections_per_ip(mut self, max: usize) -> Limits {
+        self.max_per_ip = max;
+        self
+    }
+
+    /// How long turned away clients are told to wait before trying
+    /// again, in the `Retry-After` header.
+    pub fn retry_after(mut self, retry_after: Duration) -> Limits {
+        self.retry_after = retry_after;
+        self
+    }
+
+    /// Largest request body allowed, in bytes. Bigger requests get a
+    /// `413 Payload Too Large` before their body is read. `0` means no
+    /// limit, which is the default.
+    pub fn max_body_size(mut self, bytes: u64) -> Limits {
+        self.max_body_size = bytes;
+        self
+    }
+
+    /// Reject requests that servers and proxies might disagree about,
+    /// which could let one request be smuggled inside another: more
+    /// than one `Content-Length` or one that isn't a number,
+    /// `Content-Length` with `Transfer-Encoding`, invalid header names,
+    /// control characters in header values, and too many headers.
+    /// They get a `400 Bad Request`, or a `431` for too many headers.
+    /// Off by default.
+    pub fn strict_parsing(mut self, strict: bool) -> Limits {
+        self.strict = strict;
+        self
+    }
+
+    /// Most headers a request can have in strict mode. Defaults to 100.
+    pub fn max_headers(mut self, max: usize) -> Limits {
+        self.max_headers = max;
+        self
+    }
+
+    /// Parse a raw request with these limits.
+    pub(crate) fn parse(&self, buffer: Vec<u8>) -> Result<Status> {
+        if self.strict {
+            http_parser::parse_strict(buffer, self.max_headers)
+        } else {
+            http_parser::parse(buffer)
+        }
+    }
+
+    /// Is a body of `len` bytes allowed?
+    pub(crate) fn allows_body(&self, len: u64) -> bool {
+        self.max_body_size == 0 || len <= self.max_body_size
+    }
+
+    /// Is there room in the queue with `queued` connections waiting?
+    pub(crate) fn has_room(&self, queued: usize) -> bool {
+        queued < self.max_pending
+    }
+}
+
+/// Turn a connection away with a `503 Service Unavailable`.
+pub(crate) fn reject(stream: Stream, limits: Arc<Limits>) -> Result<()> {
+    println!("- 503 -");
+    let retry_after = limits.retry_after.as_secs().to_string();
+    Response::from(503)
+        .with_header("Retry-After", &retry_after)
+        .write(Timed::writer(stream, limits)?)
+}
+
+/// Open connections for each client IP.
+#[derive(Default)]
+pub(crate) struct Clients {
+    open: Mutex<HashMap<IpAddr, usize>>,
+}
+
+impl Clients {
+    /// Count a new connection from `ip`, or return `None` if it
+    /// already has as many open as `limits` allow. The connection is
+    /// counted until the returned `Client` is dropped.
+    pub(crate) fn connect(self: &Arc<Self>, ip: IpAddr, limits: &Limits) -> Option<Client> {
+        let mut open = self.open.lock().unwrap();
+        let count = open.entry(ip).or_insert(0);
+        if limits.max_per_ip > 0 && *count >= limits.max_per_ip {
+            return None;
+        }
+        *count += 1;
+        Some(Client {
+            clients: self.clone(),
+            ip,
+        })
+    }
+}
+
+/// An open connection, counted against its IP.
+pub(crate) struct Client {
+    clients: Arc<Clients>,
+    ip: IpAddr,
+}
+
+impl Drop for Client {
+    fn drop(&mut self) {
+        let mut open = self.clients.open.lock().unwrap();
+        if let Some(count) = open.get_mut(&self.ip) {
+            *count -= 1;
+            if *count == 0 {
+                open.remove(&self.ip);
+            }
+        }
+    }
+}
+
+/// Get `stream` ready to be handed off to a WebSocket or event stream,
+/// which can sit idle for as long as it likes but still shouldn't
+/// block forever writing to a client that's gone away.
+pub(crate) fn hand_off(stream: &Stream) -> io::Result<()> {
+    stream.set_read_timeout(None)?;
+    stream.set_write_timeout(Some(current().write_timeout))
+}
+
+/// Is `bytes` over `elapsed` slower than `min` bytes per second?
+fn too_slow(bytes: u64, elapsed: Duration, min: u64) -> bool {
+    min > 0 && elapsed > RATE_GRACE_PERIOD && (bytes as f64) < min as f64 * elapsed.as_secs_f64()
+}
+
+fn timed_out() -> io::Error {
+    io::Error::new(io::ErrorKind::TimedOut, "client too slow")
+}
+
+/// Turn the `WouldBlock` some platforms return when a socket times
+/// out into `TimedOut`.
+fn timeout_kind(e: io::Error) -> io::Error {
+    match e.kind() {
+        io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut => timed_out(),
+        _ => e,
+    }
+}
+
+/// A connection that enforces `Limits` on every read and write.
+pub(crate) struct Timed {
+    stream: Stream,
+    limits: Arc<Limits>,
+    in_body: bool,
+    started: Instant,
+    bytes: u64,
+}
+
+impl Timed {
+    /// Start timing `stream`, beginning with the request headers.
+    pub(crate) fn new(stream: Stream, limits: Arc<Limits>) -> Timed {
+        Timed {
+            stream,
+            limits,
+            in_body: false,
+            started: Instant::now(),
+            bytes: 0,
+        }
+    }
+
+    /// Time writing a response to `stream`.
+    pub(crate) fn writer(stream: Stream, limits: Arc<Limits>) -> io::Result<Timed> {
+        stream.set_write_timeout(Some(limits.write_timeout))?;
+        let mut timed = Timed::new(stream, limits);
+        timed.start_body();
+        Ok(timed)
+    }
+
+    /// The headers are in; time the body from here on.
+    pub(crate) fn start_body(&mut self) {
+        self.in_body = true;
+        self.started = Instant::now();
+        self.bytes = 0;
+    }
+}
+
+impl Read for Timed {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let timeout = if self.in_body {
+            self.limits.body_timeout
+        } else {
+            let left = self
+                .limits
+                .header_timeout
+                .saturating_sub(self.started.elapsed());
+            if left.is_zero() {
+                return Err(timed_out());
+            }
+            left
+        };
+        self.stream.set_read_timeout(Some(timeout))?;
+
+        let n = self.stream.read(buf).map_err(timeout_kind)?;
+        self.bytes += n as u64;
+        if self.in_body
+            && too_slow(
+                self.bytes,
+                self.started.elapsed(),
+                self.limits.min_read_rate,
+            )
+        {
+            return Err(timed_out());
+        }
+        Ok(n)
+    }
+}
+
+impl Write for Timed {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.stream.write(buf).map_err(timeout_kind)?;
+        self.bytes += n as u64;
+        if too_slow(
+            self.bytes,
+            self.started.elapsed(),
+            self.limits.min_write_rate,
+        ) {
+            return Err(timed_out());
+        }
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.stream.flush().map_err(timeout_kind)
+    }
+}
@@ -0,0 +1,553 @@
+//! Sockets Vial can accept connections on: TCP, Unix domain sockets,
+//! and sockets passed in by systemd, along with options for each.
+use {
+    crate::{util, Error, Request, Response, Result},
+    std::{
+        fmt,
+        io::{self, Read, Write},
+        net::{
+            IpAddr, Ipv4Addr, Ipv6Addr, Shutdown, SocketAddr, SocketAddrV4, SocketAddrV6,
+            TcpListener, TcpStream, ToSocketAddrs,
+        },
+        sync::atomic::{AtomicBool, Ordering},
+        time::Duration,
+    },
+};
+
+#[cfg(unix)]
+use crate::restart;
+
+#[cfg(unix)]
+use std::{
+    env, fs,
+    os::unix::{
+        fs::{FileTypeExt, PermissionsExt},
+        io::{AsRawFd, FromRawFd, IntoRawFd, RawFd},
+        net::{UnixListener, UnixStream},
+    },
+    path::{Path, PathBuf},
+};
+
+/// First file descriptor systemd passes sockets on.
+#[cfg(unix)]
+const SD_LISTEN_FDS_START: RawFd = 3;
+
+/// What kind of socket a request came in on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ListenerKind {
+    /// A TCP socket.
+    Tcp,
+    /// A Unix domain socket.
+    Unix,
+}
+
+/// A socket to serve your app on. Pass one, or a `Vec` of them, to
+/// [`vial::run!()`](macro.run.html) in place of an address:
+///
+/// ```no_run
+/// use vial::Listener;
+///
+/// vial::routes! {
+///     GET "/" => |_| "Hello, world!";
+///     GET "/admin/stats" => |_| "All good.";
+/// }
+///
+/// fn main() -> vial::Result<()> {
+///     let public = Listener::tcp("0.0.0.0:80")?.except("/admin/*");
+///     let admin = Listener::tcp("127.0.0.1:8080")?.named("admin");
+///     vial::run!(vec![public, admin])
+/// }
+/// ```
+///
+/// Every listener shares the same routes and threads, but each can
+/// hide some routes or run its own filters.
+pub struct Listener {
+    socket: Socket,
+    options: Options,
+}
+
+/// The socket itself.
+enum Socket {
+    Tcp(TcpListener),
+    #[cfg(unix)]
+    Unix(UnixListener, PathBuf),
+}
+
+/// Which requests a listener lets through.
+#[derive(Default)]
+pub(crate) struct Options {
+    name: Option<String>,
+    only: Vec<String>,
+    except: Vec<String>,
+    head_filters: Vec<fn(&mut Request) -> Option<Response>>,
+    filters: Vec<fn(&mut Request) -> Option<Response>>,
+}
+
+impl Options {
+    /// The listener's name, if it has one.
+    pub(crate) fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// Run the listener's path checks and head filters on `req`
+    /// before its body is read, returning a response if one of them
+    /// stops it.
+    pub(crate) fn check_head(&self, req: &mut Request) -> Option<Response> {
+        let path = req.path();
+        if (!self.only.is_empty() && !self.only.iter().any(|p| util::path_matches(p, path)))
+            || self.except.iter().any(|p| util::path_matches(p, path))
+        {
+            return Some(Response::from(404));
+        }
+        self.head_filters.iter().find_map(|filter| filter(req))
+    }
+
+    /// Run the listener's filters on `req` once its body has been
+    /// read, returning a response if one of them stops it.
+    pub(crate) fn filter(&self, req: &mut Request) -> Option<Response> {
+        self.filters.iter().find_map(|filter| filter(req))
+    }
+}
+
+impl fmt::Debug for Listener {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Listener({})", self)
+    }
+}
+
+/// The listener's address, as it's shown in the startup banner.
+impl fmt::Display for Listener {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.socket {
+            Socket::Tcp(listener) => match listener.local_addr() {
+                Ok(addr) => write!(f, "http://{}", addr),
+                Err(_) => write!(f, "http://?"),
+            },
+            #[cfg(unix)]
+            Socket::Unix(_, path) => write!(f, "unix:{}", path.display()),
+        }
+    }
+}
+
+impl Listener {
+    fn new(socket: Socket) -> Listener {
+        Listener {
+            socket,
+            options: Options::default(),
+        }
+    }
+
+    /// Listen for TCP connections on `addr`. After a
+    /// [restart](restart/index.html), the socket the old process was
+    /// listening on is used instead.
+    pub fn tcp<T: ToSocketAddrs>(addr: T) -> Result<Listener> {
+        let addrs: Vec<_> = addr.to_socket_addrs()?.collect();
+        #[cfg(unix)]
+        if let Some(listener) = restart::take(|l| l.is_tcp_on(&addrs)) {
+            return Ok(listener);
+        }
+        Ok(Listener::new(Socket::Tcp(TcpListener::bind(&addrs[..])?)))
+    }
+
+    /// Listen on a Unix domain socket at `path`, like one `nginx`
+    /// proxies to. A socket left over at `path` from an earlier run
+    /// is removed first, but any other kind of file is an error.
+    #[cfg(unix)]
+    pub fn unix<P: AsRef<Path>>(path: P) -> Result<Listener> {
+        let path = path.as_ref();
+        if let Some(listener) = restart::take(|l| l.is_unix_at(path)) {
+            return Ok(listener);
+        }
+        if let Ok(meta) = fs::symlink_metadata(path) {
+            if !meta.file_type().is_socket() {
+                return Err(Error::Other(format!(
+                    "{} exists and isn't a socket",
+                    path.display()
+                )));
+            }
+            fs::remove_file(path)?;
+        }
+        Ok(Listener::new(Socket::Unix(
+            UnixListener::bind(path)?,
+            path.to_path_buf(),
+        )))
+    }
+
+    /// Set the permissions of a Unix domain socket's file, like
+    /// `0o660` to let only its owner and group connect. Does nothing
+    /// for TCP listeners.
+    #[cfg(unix)]
+    pub fn set_permissions(&self, mode: u32) -> Result<()> {
+        if let Socket::Unix(_, path) = &self.socket {
+            fs::set_permissions(path, fs::Permissions::from_mode(mode))?;
+        }
+        Ok(())
+    }
+
+    /// Use the first socket passed in by systemd socket activation,
+    /// through the `LISTEN_PID` and `LISTEN_FDS` environment variables.
+    /// Both are removed so child processes don't try to use the
+    /// socket too.
+    #[cfg(unix)]
+    pub fn systemd() -> Result<Listener> {
+        let mut listeners = Listener::systemd_all()?;
+        Ok(listeners.remove(0))
+    }
+
+    /// Use every socket passed in by systemd socket activation, in
+    /// the order they're listed in the `.socket` unit.
+    #[cfg(unix)]
+    pub fn systemd_all() -> Result<Vec<Listener>> {
+        let no_socket = || Error::Other("No socket passed in by systemd".into());
+        let pid = env::var("LISTEN_PID").map_err(|_| no_socket())?;
+        let fds = env::var("LISTEN_FDS").map_err(|_| no_socket())?;
+        if pid.trim().parse::<u32>().ok() != Some(std::process::id()) {
+            return Err(no_socket());
+        }
+        let fds = fds.trim().parse::<RawFd>().unwrap_or(0);
+        if fds < 1 {
+            return Err(no_socket());
+        }
+        env::remove_var("LISTEN_PID");
+        env::remove_var("LISTEN_FDS");
+        env::remove_var("LISTEN_FDNAMES");
+
+        // Safety: systemd hands these descriptors to us and nothing
+        // else in the process owns them.
+        Ok((SD_LISTEN_FDS_START..SD_LISTEN_FDS_START + fds)
+            .map(|fd| unsafe { Listener::from_fd(fd) })
+            .collect())
+    }
+
+    /// Take ownership of a listening socket, working out whether it's
+    /// TCP or a Unix domain socket.
+    ///
+    /// # Safety
+    /// `fd` must be an open, listening socket that nothing else owns.
+    #[cfg(unix)]
+    pub(crate) unsafe fn from_fd(fd: RawFd) -> Listener {
+        let tcp = TcpListener::from_raw_fd(fd);
+        if tcp.local_addr().is_ok() {
+            return Listener::new(Socket::Tcp(tcp));
+        }
+        let unix = UnixListener::from_raw_fd(tcp.into_raw_fd());
+        let path = unix
+            .local_addr()
+            .ok()
+            .and_then(|addr| addr.as_pathname().map(|p| p.to_path_buf()))
+            .unwrap_or_default();
+        Listener::new(Socket::Unix(unix, path))
+    }
+
+    /// Give the listener a name, which requests that come in on it
+    /// report in
+    /// [`Request::listener_name()`](struct.Request.html#method.listener_name).
+    pub fn named(mut self, name: &str) -> Listener {
+        self.options.name = Some(name.to_string());
+        self
+    }
+
+    /// Only serve paths matching `pattern` on this listener. Other
+    /// paths get a `404`. A pattern ending in `*` matches every path
+    /// that starts with it, like `"/api/*"`. Can be called more than
+    /// once.
+    pub fn only(mut self, pattern: &str) -> Listener {
+        self.options.only.push(pattern.to_string());
+        self
+    }
+
+    /// Don't serve paths matching `pattern` on this listener, like
+    /// `"/admin/*"` on a public port. They get a `404`.
+    pub fn except(mut self, pattern: &str) -> Listener {
+        self.options.except.push(pattern.to_string());
+        self
+    }
+
+    /// Run `filter` on every request to this listener, before any
+    /// route filters.
+    pub fn filter(mut self, filter: fn(&mut Request) -> Option<Response>) -> Listener {
+        self.options.filters.push(filter);
+        self
+    }
+
+    /// Run `filter` on every request to this listener as soon as its
+    /// headers are in, before its body is read. It can't see the
+    /// body, but uploads it turns away never have to be sent - a
+    /// client waiting on `Expect: 100-continue` gets the response
+    /// instead of a `100 Continue`.
+    pub fn filter_head(mut self, filter: fn(&mut Request) -> Option<Response>) -> Listener {
+        self.options.head_filters.push(filter);
+        self
+    }
+
+    /// What kind of socket this is.
+    pub fn kind(&self) -> ListenerKind {
+        match self.socket {
+            Socket::Tcp(..) => ListenerKind::Tcp,
+            #[cfg(unix)]
+            Socket::Unix(..) => ListenerKind::Unix,
+        }
+    }
+
+    /// Is this a TCP socket listening on one of `addrs`?
+    #[cfg(unix)]
+    pub(crate) fn is_tcp_on(&self, addrs: &[SocketAddr]) -> bool {
+        match &self.socket {
+            Socket::Tcp(listener) => listener
+                .local_addr()
+                .is_ok_and(|addr| addrs.contains(&addr)),
+            Socket::Unix(..) => false,
+        }
+    }
+
+    /// Is this a Unix domain socket at `path`?
+    #[cfg(unix)]
+    pub(crate) fn is_unix_at(&self, path: &Path) -> bool {
+        matches!(&self.socket, Socket::Unix(_, p) if p == path)
+    }
+
+    /// Split into the socket and its options.
+    pub(crate) fn into_parts(self) -> (Acceptor, Options) {
+        (Acceptor(self.socket), self.options)
+    }
+}
+
+#[cfg(unix)]
+impl AsRawFd for Listener {
+    fn as_raw_fd(&self) -> RawFd {
+        self.socket.as_raw_fd()
+    }
+}
+
+#[cfg(unix)]
+impl AsRawFd for Socket {
+    fn as_raw_fd(&self) -> RawFd {
+        match self {
+            Socket::Tcp(listener) => listener.as_raw_fd(),
+            Socket::Unix(listener, _) => listener.as_raw_fd(),
+        }
+    }
+}
+
+/// How long a nonblocking `Acceptor` waits for a connection before
+/// checking whether it should stop.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// The socket half of a `Listener`, which waits for connections.
+pub(crate) struct Acceptor(Socket);
+
+impl Acceptor {
+    /// Wait for connections without blocking, so `accept()` can
+    /// notice when it's told to stop.
+    pub(crate) fn set_nonblocking(&self) -> io::Result<()> {
+        match &self.0 {
+            Socket::Tcp(listener) => listener.set_nonblocking(true),
+            #[cfg(unix)]
+            Socket::Unix(listener, _) => listener.set_nonblocking(true),
+        }
+    }
+
+    /// Wait for the next connection, or `None` once `stop` is set.
+    /// Only nonblocking acceptors notice `stop`.
+    pub(crate) fn accept(&self, stop: &AtomicBool) -> io::Result<Option<Stream>> {
+        loop {
+            if stop.load(Ordering::SeqCst) {
+                return Ok(None);
+            }
+            let stream = match &self.0 {
+                Socket::Tcp(listener) => listener.accept().map(|(s, _)| Stream::Tcp(s)),
+                #[cfg(unix)]
+                Socket::Unix(listener, _) => listener.accept().map(|(s, _)| Stream::Unix(s)),
+            };
+            match stream {
+                Ok(stream) => {
+                    // the socket may have been made nonblocking, here or
+                    // by the process that handed it to us, and some
+                    // platforms pass that on to accepted connections
+                    stream.set_nonblocking(false)?;
+                    return Ok(Some(stream));
+                }
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => self.wait(),
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Wait up to `POLL_INTERVAL` for a connection to come in.
+    #[cfg(unix)]
+    fn wait(&self) {
+        let mut poll = libc::pollfd {
+            fd: self.0.as_raw_fd(),
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        // errors, like being interrupted by a signal, just mean
+        // trying accept() again
+        unsafe { libc::poll(&mut poll, 1, POLL_INTERVAL.as_millis() as libc::c_int) };
+    }
+
+    #[cfg(not(unix))]
+    fn wait(&self) {
+        std::thread::sleep(POLL_INTERVAL);
+    }
+}
+
+/// Anything [`vial::run()`](fn.run.html) can serve on: an address like
+/// `"0.0.0.0:7667"`, a [`Listener`](struct.Listener.html), or a `Vec`
+/// of listeners.
+pub trait Bind {
+    /// Open the sockets.
+    fn bind(self) -> Result<Vec<Listener>>;
+}
+
+/// Addresses are bound like `TcpListener::bind()` binds them.
+macro_rules! bind_addr {
+    ($($ty:ty),+) => {
+        $(
+            impl Bind for $ty {
+                fn bind(self) -> Result<Vec<Listener>> {
+                    Ok(vec![Listener::tcp(self)?])
+                }
+            }
+        )+
+    };
+}
+
+bind_addr!(
+    &str,
+    String,
+    &String,
+    SocketAddr,
+    SocketAddrV4,
+    SocketAddrV6,
+    (IpAddr, u16),
+    (Ipv4Addr, u16),
+    (Ipv6Addr, u16),
+    (&str, u16),
+    (String, u16),
+    &[SocketAddr]
+);
+
+impl Bind for Listener {
+    fn bind(self) -> Result<Vec<Listener>> {
+        Ok(vec![self])
+    }
+}
+
+impl Bind for Vec<Listener> {
+    fn bind(self) -> Result<Vec<Listener>> {
+        if self.is_empty() {
+            return Err(Error::Other("No listeners to serve on".into()));
+        }
+        Ok(self)
+    }
+}
+
+/// Address Unix domain socket clients show up as, since they don't
+/// have one. Trust it with `proxy::trust("127.0.0.1")` to believe the
+/// forwarded headers from a proxy like `nginx`.
+pub(crate) const UNIX_PEER: SocketAddr = SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 0);
+
+/// A connection from a client.
+#[derive(Debug)]
+pub(crate) enum Stream {
+    Tcp(TcpStream),
+    #[cfg(unix)]
+    Unix(UnixStream),
+}
+
+impl Stream {
+    pub(crate) fn try_clone(&self) -> io::Result<Stream> {
+        match self {
+            Stream::Tcp(s) => s.try_clone().map(Stream::Tcp),
+            #[cfg(unix)]
+            Stream::Unix(s) => s.try_clone().map(Stream::Unix),
+        }
+    }
+
+    /// Kind of listener this connection came in on.
+    pub(crate) fn kind(&self) -> ListenerKind {
+        match self {
+            Stream::Tcp(..) => ListenerKind::Tcp,
+            #[cfg(unix)]
+            Stream::Unix(..) => ListenerKind::Unix,
+        }
+    }
+
+    /// The client's address. Unix domain socket clients don't have one
+    /// and show up as `127.0.0.1:0`.
+    pub(crate) fn peer_addr(&self) -> io::Result<SocketAddr> {
+        match self {
+            Stream::Tcp(s) => s.peer_addr(),
+            #[cfg(unix)]
+            Stream::Unix(..) => Ok(UNIX_PEER),
+        }
+    }
+
+    pub(crate) fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()> {
+        match self {
+            Stream::Tcp(s) => s.set_nonblocking(nonblocking),
+            #[cfg(unix)]
+            Stream::Unix(s) => s.set_nonblocking(nonblocking),
+        }
+    }
+
+    pub(crate) fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        match self {
+            Stream::Tcp(s) => s.set_read_timeout(timeout),
+            #[cfg(unix)]
+            Stream::Unix(s) => s.set_read_timeout(timeout),
+        }
+    }
+
+    pub(crate) fn set_write_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        match self {
+            Stream::Tcp(s) => s.set_write_timeout(timeout),
+            #[cfg(unix)]
+            Stream::Unix(s) => s.set_write_timeout(timeout),
+        }
+    }
+
+    pub(crate) fn shutdown(&self, how: Shutdown) -> io::Result<()> {
+        match self {
+            Stream::Tcp(s) => s.shutdown(how),
+            #[cfg(unix)]
+            Stream::Unix(s) => s.shutdown(how),
+        }
+    }
+}
+
+impl From<TcpStream> for Stream {
+    fn from(stream: TcpStream) -> Stream {
+        Stream::Tcp(stream)
+    }
+}
+
+impl Read for Stream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Stream::Tcp(s) => s.read(buf),
+            #[cfg(unix)]
+            Stream::Unix(s) => s.read(buf),
+        }
+    }
+}
+
+impl Write for Stream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Stream::Tcp(s) => s.write(buf),
+            #[cfg(unix)]
+            Stream::Unix(s) => s.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Stream::Tcp(s) => s.flush(),
+            #[cfg(unix)]
+            Stream::Unix(s) => s.flush(),
+        }
+    }
+}
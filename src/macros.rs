@@ -160,6 +160,37 @@ macro_rules! use_state {
     };
 }
 
+/// Sets the secret used to encrypt and sign sessions. Call it before
+/// [`vial::run!()`](macro.run.html) when your app starts.
+///
+/// Pass more than one secret to rotate keys: the first one is used
+/// for new sessions, while the others are still accepted when reading
+/// sessions that were created with them.
+///
+/// ```no_run
+/// vial::routes! {
+///     GET "/" => |req| req.session("name").unwrap_or("stranger").to_string();
+/// }
+///
+/// fn main() {
+///     vial::session_secret!("new secret", "old secret");
+///     vial::run!().unwrap();
+/// }
+/// ```
+///
+/// If it's never called, secrets are read from the comma separated
+/// `VIAL_SESSION_SECRET` environment variable. If that isn't set,
+/// a random secret is used and sessions won't survive a restart.
+///
+/// The `sessions` feature must be enabled in `Cargo.toml`.
+#[cfg(feature = "sessions")]
+#[macro_export]
+macro_rules! session_secret {
+    ($($secret:expr),+) => {
+        ::vial::session::set_secrets(&[$($secret),+]);
+    };
+}
+
 /// This is called by `vial::run!`. You probably should leave it be.
 #[doc(hidden)]
 #[macro_export]
@@ -394,6 +425,7 @@ macro_rules! routes {
 
         $(
             $(#[filter($($action_filter:ident),+)])*
+            $(#[skip($($skip_filter:ident),+)])*
             $method:ident $path:expr => $body:expr;)*
         ) => {
         fn vial_check_method() {
@@ -407,10 +439,12 @@ macro_rules! routes {
             $($method();)*
         }
 
-        fn vial_filter(req: &mut ::vial::Request) -> Option<::vial::Response> {
+        fn vial_filter(req: &mut ::vial::Request, skip: &[&str]) -> Option<::vial::Response> {
             $($({
-                if let Some(res) = $all_filter(req) {
-                    return Some(res);
+                if !skip.contains(&stringify!($all_filter)) {
+                    if let Some(res) = $all_filter(req) {
+                        return Some(res);
+                    }
                 }
             })+)*
 
@@ -422,7 +456,7 @@ macro_rules! routes {
                 use ::vial::{Request, Response, Responder};
 
                 let b: fn(::vial::Request) -> _ = $body;
-                let mut res = vial_filter(&mut req);
+                let mut res = vial_filter(&mut req, &[$($(stringify!($skip_filter)),+)*]);
 
                 $($({
                     if res.is_none() {
@@ -0,0 +1,273 @@
+//! Content types for files and assets.
+//!
+//! [`Response::with_file()`](../struct.Response.html#method.with_file)
+//! and [`with_asset()`](../struct.Response.html#method.with_asset)
+//! pick a `Content-Type` from the file's extension using a built-in
+//! table. Apps can add their own extensions, or change the type of
+//! one that's already known, before they start:
+//!
+//! ```no_run
+//! use vial::{prelude::*, mime};
+//!
+//! routes! {
+//!     GET "/*path" => |req| Response::from_asset(req.arg("path").unwrap_or("index.html"));
+//! }
+//!
+//! fn main() {
+//!     mime::add("gmi", "text/gemini");
+//!     mime::add("md", "text/markdown; charset=utf8");
+//!     mime::use_sniffing(true);
+//!     asset_dir!("./public");
+//!     run!().unwrap();
+//! }
+//! ```
+//!
+//! Files with an extension nobody knows about are sent as
+//! `application/octet-stream`, so browsers download them instead of
+//! guessing. Files with no extension at all are too, unless sniffing
+//! is turned on with [`use_sniffing()`](fn.use_sniffing.html), in
+//! which case the first few bytes are checked for a known signature,
+//! like the one that starts every PNG.
+use std::{
+    borrow::Cow,
+    io::Read,
+    str,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        RwLock,
+    },
+};
+
+/// Content types added by the app, by lowercase extension.
+static TYPES: RwLock<Vec<(String, String)>> = RwLock::new(Vec::new());
+
+/// Whether files without an extension get sniffed.
+static SNIFF: AtomicBool = AtomicBool::new(false);
+
+/// Type for files we can't figure out.
+pub const DEFAULT: &str = "application/octet-stream";
+
+/// How much of a file is read when sniffing.
+const SNIFF_LEN: u64 = 512;
+
+/// Send files ending in `.ext` with the given content type, replacing
+/// the built-in type if there is one. `ext` is case insensitive and
+/// may start with a `.`. Should be called before your app starts.
+pub fn add(ext: &str, content_type: &str) {
+    let ext = ext.trim_start_matches('.').to_ascii_lowercase();
+    let mut types = TYPES.write().unwrap();
+    types.retain(|(e, _)| *e != ext);
+    types.push((ext, content_type.to_string()));
+}
+
+/// Sniff the content type of files that don't have an extension.
+/// Should be called before your app starts.
+pub fn use_sniffing(on: bool) {
+    SNIFF.store(on, Ordering::SeqCst);
+}
+
+/// Are files without an extension sniffed?
+pub fn uses_sniffing() -> bool {
+    SNIFF.load(Ordering::SeqCst)
+}
+
+/// Extension of the file `path` points to, if it has one.
+fn extension(path: &str) -> Option<&str> {
+    let name = path.rsplit(['/', '\\']).next().unwrap_or(path);
+    name.rsplit_once('.').map(|(_, ext)| ext)
+}
+
+/// Content type of a file based on its extension, or
+/// `application/octet-stream` if it doesn't have one we know.
+pub fn from_path(path: &str) -> Cow<'static, str> {
+    extension(path)
+        .and_then(from_extension)
+        .unwrap_or(Cow::from(DEFAULT))
+}
+
+/// Content type for an extension, like `"png"`, if we know it.
+pub fn from_extension(ext: &str) -> Option<Cow<'static, str>> {
+    let ext = ext.trim_start_matches('.').to_ascii_lowercase();
+    if let Some((_, t)) = TYPES.read().unwrap().iter().find(|(e, _)| *e == ext) {
+        return Some(Cow::from(t.clone()));
+    }
+    builtin(&ext).map(Cow::from)
+}
+
+/// Content type of a file or asset. Its extension is used if it has
+/// one, otherwise `open` is called to sniff its first few bytes if
+/// sniffing is on.
+pub(crate) fn for_file<R: Read>(path: &str, open: impl FnOnce() -> Option<R>) -> Cow<'static, str> {
+    if extension(path).is_some() || !uses_sniffing() {
+        return from_path(path);
+    }
+    let mut head = vec![];
+    if let Some(reader) = open() {
+        let _ = reader.take(SNIFF_LEN).read_to_end(&mut head);
+    }
+    Cow::from(sniff(&head).unwrap_or(DEFAULT))
+}
+
+/// Guess a content type from the first few bytes of a file, using
+/// the signatures common formats start with. Text that isn't HTML,
+/// SVG, or XML is `text/plain`.
+pub fn sniff(bytes: &[u8]) -> Option<&'static str> {
+    const SIGNATURES: &[(&[u8], &str)] = &[
+        (b"\x89PNG\r\n\x1a\n", "image/png"),
+        (b"GIF87a", "image/gif"),
+        (b"GIF89a", "image/gif"),
+        (b"\xff\xd8\xff", "image/jpeg"),
+        (b"\x00\x00\x01\x00", "image/vnd.microsoft.icon"),
+        (b"%PDF-", "application/pdf"),
+        (b"PK\x03\x04", "application/zip"),
+        (b"\x1f\x8b", "application/gzip"),
+        (b"\x00asm", "application/wasm"),
+        (b"wOFF", "font/woff"),
+        (b"wOF2", "font/woff2"),
+        (b"OggS", "application/ogg"),
+        (b"ID3", "audio/mpeg"),
+        (b"fLaC", "audio/flac"),
+        (b"\x1a\x45\xdf\xa3", "video/webm"),
+    ];
+    if let Some((_, t)) = SIGNATURES.iter().find(|(sig, _)| bytes.starts_with(sig)) {
+        return Some(t);
+    }
+
+    if bytes.starts_with(b"RIFF") && bytes.len() >= 12 {
+        return match &bytes[8..12] {
+            b"WEBP" => Some("image/webp"),
+            b"WAVE" => Some("audio/wav"),
+            b"AVI " => Some("video/x-msvideo"),
+            _ => None,
+        };
+    }
+    if bytes.len() >= 12 && &bytes[4..8] == b"ftyp" {
+        return match &bytes[8..12] {
+            b"avif" | b"avis" => Some("image/avif"),
+            b"heic" | b"heix" => Some("image/heic"),
+            b"M4A " => Some("audio/mp4"),
+            b"qt  " => Some("video/quicktime"),
+            _ => Some("video/mp4"),
+        };
+    }
+
+    // Text, as long as the only thing wrong with it is a character
+    // cut off at the end of what we read.
+    let text = match str::from_utf8(bytes) {
+        Ok(text) => text,
+        Err(e) if e.error_len().is_none() => str::from_utf8(&bytes[..e.valid_up_to()]).ok()?,
+        Err(_) => return None,
+    };
+    if text.is_empty() || text.chars().any(|c| c.is_control() && !c.is_whitespace()) {
+        return None;
+    }
+    let start = text.trim_start().to_ascii_lowercase();
+    if start.starts_with("<!doctype html") || start.starts_with("<html") {
+        Some("text/html; charset=utf8")
+    } else if start.starts_with("<svg") || (start.starts_with("<?xml") && start.contains("<svg")) {
+        Some("image/svg+xml")
+    } else if start.starts_with("<?xml") {
+        Some("application/xml")
+    } else {
+        Some("text/plain; charset=utf8")
+    }
+}
+
+/// Built-in content types by lowercase extension.
+/// https://developer.mozilla.org/en-US/docs/Web/HTTP/Basics_of_HTTP/MIME_types/Common_types
+fn builtin(ext: &str) -> Option<&'static str> {
+    Some(match ext {
+        "aac" => "audio/aac",
+        "abw" => "application/x-abiword",
+        "apng" => "image/apng",
+        "arc" => "application/x-freearc",
+        "atom" => "application/atom+xml",
+        "avi" => "video/x-msvideo",
+        "avif" => "image/avif",
+        "azw" => "application/vnd.amazon.ebook",
+        "bin" => "application/octet-stream",
+        "bmp" => "image/bmp",
+        "bz" => "application/x-bzip",
+        "bz2" => "application/x-bzip2",
+        "cjs" => "text/javascript",
+        "csh" => "application/x-csh",
+        "css" => "text/css; charset=utf8",
+        "csv" => "text/csv",
+        "doc" => "application/msword",
+        "docx" => "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
+        "eot" => "application/vnd.ms-fontobject",
+        "epub" => "application/epub+zip",
+        "flac" => "audio/flac",
+        "gif" => "image/gif",
+        "glb" => "model/gltf-binary",
+        "gltf" => "model/gltf+json",
+        "gz" | "tgz" => "application/gzip",
+        "heic" => "image/heic",
+        "htm" | "html" => "text/html; charset=utf8",
+        "ico" => "image/vnd.microsoft.icon",
+        "ics" => "text/calendar",
+        "jar" => "application/java-archive",
+        "jpeg" | "jpg" => "image/jpeg",
+        "js" => "text/javascript",
+        "json" => "application/json",
+        "jsonld" => "application/ld+json",
+        "jxl" => "image/jxl",
+        "m4a" => "audio/mp4",
+        "map" => "application/json",
+        "markdown" | "md" => "text/plain; charset=utf8",
+        "mid" | "midi" => "audio/midi",
+        "mjs" => "text/javascript",
+        "mkv" => "video/x-matroska",
+        "mov" => "video/quicktime",
+        "mp3" => "audio/mpeg",
+        "mp4" | "m4v" => "video/mp4",
+        "mpeg" => "video/mpeg",
+        "mpkg" => "application/vnd.apple.installer+xml",
+        "odp" => "application/vnd.oasis.opendocument.presentation",
+        "ods" => "application/vnd.oasis.opendocument.spreadsheet",
+        "odt" => "application/vnd.oasis.opendocument.text",
+        "oga" => "audio/ogg",
+        "ogv" => "video/ogg",
+        "ogx" => "application/ogg",
+        "opus" => "audio/opus",
+        "otf" => "font/otf",
+        "pdf" => "application/pdf",
+        "php" => "application/x-httpd-php",
+        "png" => "image/png",
+        "ppt" => "application/vnd.ms-powerpoint",
+        "pptx" => "application/vnd.openxmlformats-officedocument.presentationml.presentation",
+        "rar" => "application/vnd.rar",
+        "rss" => "application/rss+xml",
+        "rtf" => "application/rtf",
+        "sh" => "application/x-sh",
+        "svg" => "image/svg+xml",
+        "swf" => "application/x-shockwave-flash",
+        "tar" => "application/x-tar",
+        "text" | "txt" => "text/plain; charset=utf8",
+        "tif" | "tiff" => "image/tiff",
+        "toml" => "application/toml",
+        "ts" => "video/mp2t",
+        "ttf" => "font/ttf",
+        "vsd" => "application/vnd.visio",
+        "vtt" => "text/vtt",
+        "wasm" => "application/wasm",
+        "wav" => "audio/wav",
+        "weba" => "audio/webm",
+        "webm" => "video/webm",
+        "webmanifest" => "application/manifest+json",
+        "webp" => "image/webp",
+        "woff" => "font/woff",
+        "woff2" => "font/woff2",
+        "xhtml" => "application/xhtml+xml",
+        "xls" => "application/vnd.ms-excel",
+        "xlsx" => "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+        "xml" => "application/xml",
+        "xul" => "application/vnd.mozilla.xul+xml",
+        "xz" => "application/x-xz",
+        "yaml" | "yml" => "application/yaml",
+        "zip" => "application/zip",
+        "zst" => "application/zstd",
+        "7z" => "application/x-7z-compressed",
+        _ => return None,
+    })
+}
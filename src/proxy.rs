@@ -0,0 +1,394 @@
+//! Finding the real client behind a reverse proxy or load balancer.
+//!
+//! Behind a proxy, every connection comes from the proxy itself, so
+//! [`Request::remote_addr()`](../struct.Request.html#method.remote_addr)
+//! would be the proxy's address. Proxies pass the client's address
+//! along in the `X-Forwarded-For` or `Forwarded` header, but anybody
+//! can send those, so they're only believed when the connection
+//! comes from a proxy you [`trust()`](fn.trust.html):
+//!
+//! ```no_run
+//! use vial::{prelude::*, proxy};
+//!
+//! routes! {
+//!     GET "/" => |req| format!(
+//!         "Hello, {} over {}!",
+//!         req.remote_addr().ip(),
+//!         req.scheme()
+//!     );
+//! }
+//!
+//! fn main() {
+//!     proxy::trust("127.0.0.1").unwrap();
+//!     proxy::trust_with("10.0.0.0/8", proxy::Header::Forwarded).unwrap();
+//!     run!().unwrap();
+//! }
+//! ```
+//!
+//! Requests from trusted proxies get their `remote_addr()`,
+//! `scheme()`, and `host()` from the forwarded headers. Only the
+//! header the proxy itself sets is read: `X-Forwarded-For`,
+//! `X-Forwarded-Proto`, and `X-Forwarded-Host` for proxies added with
+//! `trust()`, or `Forwarded` for ones added with
+//! [`trust_with()`](fn.trust_with.html). Proxies usually pass the
+//! other header along untouched, so a client could put whatever it
+//! likes in it. The address
+//! the connection really came from is always available as
+//! [`Request::peer_addr()`](../struct.Request.html#method.peer_addr).
+//!
+//! When a request passed through several proxies, the client is the
+//! last address in the chain that isn't a trusted proxy.
+//!
+//! ## PROXY Protocol
+//!
+//! Load balancers that work at the TCP level, like HAProxy or AWS's
+//! Network Load Balancer, can't add headers. Instead they send the
+//! client's address in a [PROXY protocol] header, version 1 or 2,
+//! before the request. Turn on
+//! [`use_proxy_protocol()`](fn.use_proxy_protocol.html) and every
+//! connection must start with one:
+//!
+//! ```no_run
+//! vial::routes! {
+//!     GET "/" => |req| req.remote_addr().to_string();
+//! }
+//!
+//! fn main() {
+//!     vial::proxy::use_proxy_protocol(true);
+//!     vial::run!().unwrap();
+//! }
+//! ```
+//!
+//! The client's address then becomes both `remote_addr()` and
+//! `peer_addr()`. Connections without a valid header are dropped, so
+//! only turn this on when the load balancer is the only way in.
+//!
+//! [PROXY protocol]: https://www.haproxy.org/download/2.8/doc/proxy-protocol.txt
+use {
+    crate::{Error, Request, Result},
+    std::{
+        io::Read,
+        net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            RwLock,
+        },
+    },
+};
+
+/// Networks whose forwarded headers are believed, and which header
+/// they set.
+static TRUSTED: RwLock<Vec<(Network, Header)>> = RwLock::new(Vec::new());
+
+/// Whether connections start with a PROXY protocol header.
+static PROXY_PROTOCOL: AtomicBool = AtomicBool::new(false);
+
+/// Signature that starts a version 2 PROXY protocol header.
+const V2_SIGNATURE: &[u8; 12] = b"\r\n\r\n\0\r\nQUIT\n";
+
+/// Longest a version 1 PROXY protocol header can be.
+const V1_MAX_LEN: usize = 107;
+
+/// Header a trusted proxy uses to pass the client along.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Header {
+    /// `X-Forwarded-For`, with `X-Forwarded-Proto` and
+    /// `X-Forwarded-Host`. Sent by nginx, most load balancers, and
+    /// [`trust()`](fn.trust.html)'s default.
+    XForwardedFor,
+    /// The standard `Forwarded` header (RFC 7239).
+    Forwarded,
+}
+
+/// Believe the `X-Forwarded-*` headers on connections from
+/// `network`, which is either an IP address like `"10.1.2.3"` or a
+/// CIDR block like `"10.0.0.0/8"` or `"fd00::/8"`.
+pub fn trust(network: &str) -> Result<()> {
+    trust_with(network, Header::XForwardedFor)
+}
+
+/// Believe `header` on connections from `network`, which should be
+/// the header that proxy sets. Any other forwarded header on those
+/// connections is ignored.
+pub fn trust_with(network: &str, header: Header) -> Result<()> {
+    let network = Network::parse(network)
+        .ok_or_else(|| Error::Other(format!("Invalid proxy network: {}", network)))?;
+    TRUSTED.write().unwrap().push((network, header));
+    Ok(())
+}
+
+/// Stop trusting any proxies.
+pub fn clear() {
+    TRUSTED.write().unwrap().clear();
+}
+
+/// Expect every connection to start with a PROXY protocol header.
+/// Should be called before your app starts.
+pub fn use_proxy_protocol(on: bool) {
+    PROXY_PROTOCOL.store(on, Ordering::SeqCst);
+}
+
+/// Are connections expected to start with a PROXY protocol header?
+pub fn uses_proxy_protocol() -> bool {
+    PROXY_PROTOCOL.load(Ordering::SeqCst)
+}
+
+/// Read a PROXY protocol header, version 1 or 2, from the start of a
+/// connection, leaving the request after it unread. Returns the
+/// client's address, or `None` if the load balancer didn't send one,
+/// like for its own health checks.
+pub(crate) fn read_proxy_header<R: Read>(reader: &mut R) -> Result<Option<SocketAddr>> {
+    // both versions are at least this long
+    let mut start = [0u8; 12];
+    reader.read_exact(&mut start)?;
+    if &start == V2_SIGNATURE {
+        read_v2(reader)
+    } else if start.starts_with(b"PROXY ") {
+        read_v1(reader, &start)
+    } else {
+        Err(Error::ProxyProtocol)
+    }
+}
+
+/// Version 1: `PROXY TCP4 192.0.2.1 198.51.100.1 56324 443\r\n`
+fn read_v1<R: Read>(reader: &mut R, start: &[u8]) -> Result<Option<SocketAddr>> {
+    let mut line = start.to_vec();
+    let mut byte = [0u8; 1];
+    while !line.ends_with(b"\r\n") {
+        if line.len() >= V1_MAX_LEN {
+            return Err(Error::ProxyProtocol);
+        }
+        reader.read_exact(&mut byte)?;
+        line.push(byte[0]);
+    }
+
+    let line = std::str::from_utf8(&line[..line.len() - 2]).map_err(|_| Error::ProxyProtocol)?;
+    let parts: Vec<&str> = line.split(' ').collect();
+    match parts.as_slice() {
+        ["PROXY", "UNKNOWN", ..] => Ok(None),
+        ["PROXY", family @ ("TCP4" | "TCP6"), src, _dst, sport, _dport] => {
+            let ip: IpAddr = src.parse().map_err(|_| Error::ProxyProtocol)?;
+            let port: u16 = sport.parse().map_err(|_| Error::ProxyProtocol)?;
+            if ip.is_ipv4() != (*family == "TCP4") {
+                return Err(Error::ProxyProtocol);
+            }
+            Ok(Some(SocketAddr::new(ip, port)))
+        }
+        _ => Err(Error::ProxyProtocol),
+    }
+}
+
+/// Version 2: binary, after the signature.
+fn read_v2<R: Read>(reader: &mut R) -> Result<Option<SocketAddr>> {
+    let mut head = [0u8; 4];
+    reader.read_exact(&mut head)?;
+    let (version, command, family) = (head[0] >> 4, head[0] & 0x0f, head[1]);
+    let len = u16::from_be_bytes([head[2], head[3]]) as usize;
+    if version != 2 || command > 1 {
+        return Err(Error::ProxyProtocol);
+    }
+
+    // always read the whole thing, TLVs and all, to get to the request
+    let mut data = vec![0u8; len];
+    reader.read_exact(&mut data)?;
+
+    // LOCAL: a connection from the load balancer itself
+    if command == 0 {
+        return Ok(None);
+    }
+    match family {
+        // TCP or UDP over IPv4
+        0x11 | 0x12 => {
+            if len < 12 {
+                return Err(Error::ProxyProtocol);
+            }
+            let ip = Ipv4Addr::new(data[0], data[1], data[2], data[3]);
+            let port = u16::from_be_bytes([data[8], data[9]]);
+            Ok(Some(SocketAddr::new(IpAddr::V4(ip), port)))
+        }
+        // TCP or UDP over IPv6
+        0x21 | 0x22 => {
+            if len < 36 {
+                return Err(Error::ProxyProtocol);
+            }
+            let mut octets = [0u8; 16];
+            octets.copy_from_slice(&data[..16]);
+            let port = u16::from_be_bytes([data[32], data[33]]);
+            Ok(Some(SocketAddr::new(
+                IpAddr::V6(Ipv6Addr::from(octets)),
+                port,
+            )))
+        }
+        // unix sockets and unspecified: nothing we can use
+        _ => Ok(None),
+    }
+}
+
+/// Is `ip` a trusted proxy?
+pub fn is_trusted(ip: IpAddr) -> bool {
+    trusted_header(ip).is_some()
+}
+
+/// Header the trusted proxy at `ip` sets, if it's trusted.
+fn trusted_header(ip: IpAddr) -> Option<Header> {
+    TRUSTED
+        .read()
+        .unwrap()
+        .iter()
+        .find(|(n, _)| n.contains(ip))
+        .map(|(_, header)| *header)
+}
+
+/// An IP address range.
+#[derive(Debug, Clone, Copy)]
+struct Network {
+    addr: IpAddr,
+    prefix: u8,
+}
+
+impl Network {
+    fn parse(s: &str) -> Option<Network> {
+        let (addr, prefix) = match s.trim().split_once('/') {
+            Some((addr, prefix)) => (addr.parse().ok()?, Some(prefix.parse().ok()?)),
+            None => (s.trim().parse().ok()?, None),
+        };
+        let addr = canonical(addr);
+        let max = if addr.is_ipv4() { 32 } else { 128 };
+        let prefix = prefix.unwrap_or(max);
+        if prefix > max {
+            return None;
+        }
+        Some(Network { addr, prefix })
+    }
+
+    fn contains(&self, ip: IpAddr) -> bool {
+        match (self.addr, canonical(ip)) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => prefix_eq(
+                u32::from(net) as u128,
+                u32::from(ip) as u128,
+                32,
+                self.prefix,
+            ),
+            (IpAddr::V6(net), IpAddr::V6(ip)) => {
+                prefix_eq(u128::from(net), u128::from(ip), 128, self.prefix)
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Do the first `prefix` of `bits` bits of `a` and `b` match?
+fn prefix_eq(a: u128, b: u128, bits: u8, prefix: u8) -> bool {
+    let shift = bits - prefix;
+    shift >= 128 || a >> shift == b >> shift
+}
+
+/// IPv4 addresses mapped into IPv6, like `::ffff:10.0.0.1`, as IPv4.
+fn canonical(ip: IpAddr) -> IpAddr {
+    match ip {
+        IpAddr::V6(v6) => v6.to_ipv4_mapped().map(IpAddr::V4).unwrap_or(ip),
+        ip => ip,
+    }
+}
+
+/// Parse a forwarded node like `10.0.0.1`, `10.0.0.1:4711`, or
+/// `[2001:db8::17]:4711`. Obfuscated and `unknown` nodes give `None`.
+fn parse_node(node: &str) -> Option<SocketAddr> {
+    let node = node.trim().trim_matches('"');
+    if let Ok(addr) = node.parse::<SocketAddr>() {
+        return Some(addr);
+    }
+    let ip = node.trim_start_matches('[').trim_end_matches(']');
+    ip.parse::<IpAddr>().ok().map(|ip| SocketAddr::new(ip, 0))
+}
+
+/// One hop in a chain of proxies.
+#[derive(Debug, Default)]
+struct Hop {
+    addr: Option<SocketAddr>,
+    proto: Option<String>,
+    host: Option<String>,
+}
+
+/// Hops from the standard `Forwarded` header.
+fn forwarded(header: &str) -> Vec<Hop> {
+    header
+        .split(',')
+        .map(|element| {
+            let mut hop = Hop::default();
+            for pair in element.split(';') {
+                let (name, value) = match pair.split_once('=') {
+                    Some(pair) => pair,
+                    None => continue,
+                };
+                let value = value.trim().trim_matches('"');
+                match name.trim().to_ascii_lowercase().as_ref() {
+                    "for" => hop.addr = parse_node(value),
+                    "proto" => hop.proto = Some(value.to_ascii_lowercase()),
+                    "host" => hop.host = Some(value.to_string()),
+                    _ => {}
+                }
+            }
+            hop
+        })
+        .collect()
+}
+
+/// Hops from the `X-Forwarded-*` headers.
+fn x_forwarded(req: &Request) -> Vec<Hop> {
+    let last = |name: &str| {
+        req.header(name)
+            .and_then(|v| v.rsplit(',').next().map(|v| v.trim().to_string()))
+            .filter(|v| !v.is_empty())
+    };
+    let mut hops: Vec<Hop> = req
+        .header("X-Forwarded-For")
+        .map(|v| {
+            v.split(',')
+                .map(|node| Hop {
+                    addr: parse_node(node),
+                    ..Hop::default()
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    // the proxy we trust added the last entry
+    if let Some(hop) = hops.last_mut() {
+        hop.proto = last("X-Forwarded-Proto").map(|p| p.to_ascii_lowercase());
+        hop.host = last("X-Forwarded-Host");
+    }
+    hops
+}
+
+/// Set the request's addresses for a connection from `peer`, using
+/// forwarded headers if `peer` is a trusted proxy.
+pub(crate) fn resolve(req: &mut Request, peer: SocketAddr) {
+    req.set_peer_addr(peer);
+    req.set_remote_addr(peer);
+    let hops = match trusted_header(peer.ip()) {
+        Some(Header::Forwarded) => req
+            .header("Forwarded")
+            .map(|header| forwarded(&header))
+            .unwrap_or_default(),
+        Some(Header::XForwardedFor) => x_forwarded(req),
+        None => return,
+    };
+
+    // walk back from the nearest proxy until we find someone we
+    // don't trust: that's the client
+    let mut proto = None;
+    let mut host = None;
+    for hop in hops.into_iter().rev() {
+        let addr = match hop.addr {
+            Some(addr) => addr,
+            None => break,
+        };
+        req.set_remote_addr(addr);
+        proto = hop.proto.or(proto);
+        host = hop.host.or(host);
+        if !is_trusted(addr.ip()) {
+            break;
+        }
+    }
+    req.set_forwarded(proto, host);
+}
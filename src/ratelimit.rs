@@ -0,0 +1,332 @@
+//! Rate limiting for login forms, APIs, and anything else clients
+//! shouldn't be able to hammer.
+//!
+//! Describe the limit with a [`RateLimit`](struct.RateLimit.html),
+//! pass it to [`set()`](fn.set.html), and add the
+//! [`limit`](fn.limit.html) filter to the routes it should cover:
+//!
+//! ```no_run
+//! use {std::time::Duration, vial::{prelude::*, ratelimit::{self, limit, RateLimit}}};
+//!
+//! routes! {
+//!     GET "/" => |_| "Hello, world!";
+//!
+//!     #[filter(limit)]
+//!     POST "/login" => |_| "Welcome back!";
+//! }
+//!
+//! fn main() {
+//!     ratelimit::set(RateLimit::new(5, Duration::from_secs(60)));
+//!     run!().unwrap();
+//! }
+//! ```
+//!
+//! Requests over the limit get a `429 Too Many Requests` with
+//! `Retry-After` and `RateLimit-*` headers saying when to try again.
+//!
+//! Clients are told apart by IP address unless you pick another key,
+//! like an API key header. Routes that need different limits can use
+//! named limits, checked from your own filter:
+//!
+//! ```no_run
+//! use {std::time::Duration, vial::{prelude::*, ratelimit::{self, RateLimit}}};
+//!
+//! fn api_limit(req: &mut Request) -> Option<Response> {
+//!     ratelimit::check("api", req)
+//! }
+//!
+//! routes! {
+//!     #![filter(api_limit)]
+//!
+//!     GET "/api/items" => |_| "[]";
+//! }
+//!
+//! fn main() {
+//!     ratelimit::add("api", RateLimit::new(100, Duration::from_secs(60)).by_header("X-Api-Key"));
+//!     run!().unwrap();
+//! }
+//! ```
+//!
+//! Limits are tracked in memory, per process, and clients that have
+//! gone quiet are forgotten once their window has passed.
+use {
+    crate::{Request, Response},
+    std::{
+        collections::HashMap,
+        fmt,
+        sync::{Arc, Mutex, RwLock},
+        time::{Duration, Instant},
+    },
+};
+
+/// Limits by name. The one set with `set()` has an empty name.
+static LIMITS: RwLock<Vec<(String, Arc<RateLimit>)>> = RwLock::new(Vec::new());
+
+/// Use `limit` for the [`limit`](fn.limit.html) filter. Should be
+/// called before your app starts.
+pub fn set(limit: RateLimit) {
+    add("", limit);
+}
+
+/// Add a named limit to use with [`check()`](fn.check.html),
+/// replacing any existing limit with the same name.
+pub fn add(name: &str, limit: RateLimit) {
+    let mut limits = LIMITS.write().unwrap();
+    limits.retain(|(n, _)| n != name);
+    limits.push((name.to_string(), Arc::new(limit)));
+}
+
+/// Filter that rejects requests over the limit given to
+/// [`set()`](fn.set.html) with `429 Too Many Requests`.
+pub fn limit(req: &mut Request) -> Option<Response> {
+    check("", req)
+}
+
+/// Count `req` against the limit named `name`, returning a
+/// `429 Too Many Requests` response if it's over. Requests are let
+/// through if there's no limit by that name.
+pub fn check(name: &str, req: &Request) -> Option<Response> {
+    let limit = LIMITS
+        .read()
+        .unwrap()
+        .iter()
+        .find(|(n, _)| n == name)
+        .map(|(_, limit)| limit.clone())?;
+    limit.check(req)
+}
+
+/// How requests are counted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Algorithm {
+    /// Each client has a bucket of `limit` tokens that refills
+    /// steadily over the window. Allows short bursts.
+    TokenBucket,
+    /// Counts requests over the last window, weighting the previous
+    /// window by how much of it still overlaps. Smoother, with no
+    /// bursts at window boundaries.
+    SlidingWindow,
+}
+
+/// Pulls the key requests are counted under out of a request.
+type KeyFn = Box<dyn Fn(&Request) -> Option<String> + Send + Sync>;
+
+/// What requests are counted under.
+enum Key {
+    Ip,
+    Header(String),
+    Custom(KeyFn),
+}
+
+/// Per-client state.
+#[derive(Debug, Clone, Copy)]
+enum Entry {
+    Bucket {
+        tokens: f64,
+        updated: Instant,
+    },
+    Window {
+        started: Instant,
+        current: u32,
+        previous: u32,
+    },
+}
+
+/// Outcome of counting a request.
+struct Decision {
+    allowed: bool,
+    remaining: u32,
+    reset: Duration,
+}
+
+/// A limit of `limit` requests per `window` for each client.
+pub struct RateLimit {
+    limit: u32,
+    window: Duration,
+    algorithm: Algorithm,
+    key: Key,
+    entries: Mutex<HashMap<String, Entry>>,
+    swept: Mutex<Instant>,
+}
+
+impl fmt::Debug for RateLimit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RateLimit")
+            .field("limit", &self.limit)
+            .field("window", &self.window)
+            .field("algorithm", &self.algorithm)
+            .finish()
+    }
+}
+
+impl RateLimit {
+    /// Allow `limit` requests per `window` from each client IP, using
+    /// a token bucket.
+    pub fn new(limit: u32, window: Duration) -> RateLimit {
+        RateLimit {
+            limit: limit.max(1),
+            window: window.max(Duration::from_millis(1)),
+            algorithm: Algorithm::TokenBucket,
+            key: Key::Ip,
+            entries: Mutex::new(HashMap::new()),
+            swept: Mutex::new(Instant::now()),
+        }
+    }
+
+    /// Count requests with a different algorithm.
+    pub fn algorithm(mut self, algorithm: Algorithm) -> RateLimit {
+        self.algorithm = algorithm;
+        self
+    }
+
+    /// Count requests by client IP. This is the default.
+    pub fn by_ip(mut self) -> RateLimit {
+        self.key = Key::Ip;
+        self
+    }
+
+    /// Count requests by the value of a header, like an API key.
+    /// Requests without it are counted by IP.
+    pub fn by_header(mut self, name: &str) -> RateLimit {
+        self.key = Key::Header(name.to_string());
+        self
+    }
+
+    /// Count requests by whatever `key` returns, like a user ID from
+    /// the session. Requests it returns `None` for are counted by IP.
+    pub fn by_key<F>(mut self, key: F) -> RateLimit
+    where
+        F: Fn(&Request) -> Option<String> + Send + Sync + 'static,
+    {
+        self.key = Key::Custom(Box::new(key));
+        self
+    }
+
+    /// Count `req`, returning a `429 Too Many Requests` response if
+    /// it's over the limit.
+    pub fn check(&self, req: &Request) -> Option<Response> {
+        let key = match &self.key {
+            Key::Ip => None,
+            Key::Header(name) => req.header(name).map(|v| format!("header:{}", v)),
+            Key::Custom(f) => f(req).map(|k| format!("key:{}", k)),
+        }
+        .unwrap_or_else(|| format!("ip:{}", req.remote_addr().ip()));
+
+        let decision = self.count(&key, Instant::now());
+        if decision.allowed {
+            return None;
+        }
+
+        // round up, so clients don't come back a moment too early
+        let reset = decision.reset.as_secs() + (decision.reset.subsec_nanos() > 0) as u64;
+        let reset = reset.max(1).to_string();
+        Some(
+            Response::from(429)
+                .with_header("Retry-After", &reset)
+                .with_header("RateLimit-Limit", &self.limit.to_string())
+                .with_header("RateLimit-Remaining", &decision.remaining.to_string())
+                .with_header("RateLimit-Reset", &reset),
+        )
+    }
+
+    /// Count a request for `key` at `now`.
+    fn count(&self, key: &str, now: Instant) -> Decision {
+        self.sweep(now);
+
+        let limit = self.limit as f64;
+        let window = self.window.as_secs_f64();
+        let mut entries = self.entries.lock().unwrap();
+        let entry = entries
+            .entry(key.to_string())
+            .or_insert(match self.algorithm {
+                Algorithm::TokenBucket => Entry::Bucket {
+                    tokens: limit,
+                    updated: now,
+                },
+                Algorithm::SlidingWindow => Entry::Window {
+                    started: now,
+                    current: 0,
+                    previous: 0,
+                },
+            });
+
+        match entry {
+            Entry::Bucket { tokens, updated } => {
+                let rate = limit / window;
+                let elapsed = now.saturating_duration_since(*updated).as_secs_f64();
+                *tokens = (*tokens + elapsed * rate).min(limit);
+                *updated = now;
+
+                let allowed = *tokens >= 1.0;
+                if allowed {
+                    *tokens -= 1.0;
+                }
+                let reset = if allowed {
+                    (limit - *tokens) / rate
+                } else {
+                    (1.0 - *tokens) / rate
+                };
+                Decision {
+                    allowed,
+                    remaining: *tokens as u32,
+                    reset: Duration::from_secs_f64(reset),
+                }
+            }
+            Entry::Window {
+                started,
+                current,
+                previous,
+            } => {
+                let mut elapsed = now.saturating_duration_since(*started);
+                if elapsed >= self.window * 2 {
+                    *previous = 0;
+                    *current = 0;
+                    *started = now;
+                } else if elapsed >= self.window {
+                    *previous = *current;
+                    *current = 0;
+                    *started += self.window;
+                }
+                elapsed = now.saturating_duration_since(*started);
+
+                let overlap = 1.0 - elapsed.as_secs_f64() / window;
+                let used = *previous as f64 * overlap + *current as f64;
+                let allowed = used + 1.0 <= limit;
+                if allowed {
+                    *current += 1;
+                }
+                Decision {
+                    allowed,
+                    remaining: (limit - used - allowed as u8 as f64).max(0.0) as u32,
+                    reset: self.window - elapsed,
+                }
+            }
+        }
+    }
+
+    /// Forget clients that haven't been seen for a whole window,
+    /// since they're back to a clean slate anyway. Runs at most once
+    /// a window.
+    fn sweep(&self, now: Instant) {
+        let mut swept = self.swept.lock().unwrap();
+        if now.saturating_duration_since(*swept) < self.window {
+            return;
+        }
+        *swept = now;
+
+        let window = self.window;
+        self.entries.lock().unwrap().retain(|_, entry| match entry {
+            Entry::Bucket { updated, .. } => now.saturating_duration_since(*updated) < window,
+            Entry::Window { started, .. } => now.saturating_duration_since(*started) < window * 2,
+        });
+    }
+
+    /// How many clients are being tracked.
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().len()
+    }
+
+    /// Is nobody being tracked?
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
@@ -1,5 +1,8 @@
 use {
-    crate::{http_parser, util, Error, Result, TypeCache},
+    crate::{
+        http_parser, limits::Limits, util, Authorization, ContentType, Error, ListenerKind, Result,
+        TypeCache, Version,
+    },
     std::{
         borrow::Cow,
         collections::HashMap,
@@ -10,12 +13,20 @@ use {
     },
 };
 
-#[cfg(feature = "cookies")]
-use cookie2::Cookie;
-
 #[cfg(feature = "sessions")]
 use crate::session::{self, Session};
 
+#[cfg(feature = "csrf")]
+use {crate::csrf, std::cell::RefCell};
+
+/// Read from a client, turning timeouts into `Error::RequestTimeout`.
+fn read_some<R: io::Read>(reader: &mut R, buf: &mut [u8]) -> Result<usize> {
+    reader.read(buf).map_err(|e| match e.kind() {
+        io::ErrorKind::TimedOut => Error::RequestTimeout,
+        _ => e.into(),
+    })
+}
+
 /// A `(start, end)` tuple representing a the location of some part of
 /// a Request in a raw buffer, such as the requested URL's path.
 #[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
@@ -50,6 +61,28 @@ pub struct Request {
     /// Remote address.
     remote_addr: SocketAddr,
 
+    /// Address the connection came from, which may be a proxy.
+    peer_addr: SocketAddr,
+
+    /// Scheme reported by a trusted proxy.
+    scheme: Option<String>,
+
+    /// Host reported by a trusted proxy.
+    forwarded_host: Option<String>,
+
+    /// HTTP version the client spoke.
+    version: Version,
+
+    /// Host given in an absolute-form request target, like
+    /// `GET http://example.com/ HTTP/1.1`.
+    authority: Span,
+
+    /// Kind of socket the request came in on.
+    listener: ListenerKind,
+
+    /// Name of the listener the request came in on, if it has one.
+    listener_name: Option<String>,
+
     /// The raw request.
     buffer: Vec<u8>,
 
@@ -64,6 +97,9 @@ pub struct Request {
     /// Sent Headers
     headers: Vec<(Span, Span)>,
 
+    /// Positions in `headers`, by lowercase header name.
+    header_index: HashMap<String, Vec<usize>>,
+
     /// Request Body (POST)
     body: Span,
 
@@ -79,6 +115,11 @@ pub struct Request {
 
     #[cfg(feature = "sessions")]
     session_store: Session,
+
+    /// CSRF token created during this request, to be saved in the
+    /// session once the response is ready.
+    #[cfg(feature = "csrf")]
+    new_csrf_token: Rc<RefCell<Option<String>>>,
 }
 
 impl fmt::Debug for Request {
@@ -96,6 +137,40 @@ impl fmt::Debug for Request {
     }
 }
 
+impl Default for Request {
+    /// Produce an empty Request.
+    fn default() -> Self {
+        Self {
+            remote_addr: SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 0),
+            peer_addr: SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 0),
+            scheme: None,
+            forwarded_host: None,
+            version: Version::default(),
+            authority: Span::new(),
+            listener: ListenerKind::Tcp,
+            listener_name: None,
+            path: Span::new(),
+            method: Span::new(),
+            body: Span::new(),
+            headers: Vec::new(),
+            header_index: HashMap::new(),
+            args: HashMap::new(),
+            form: HashMap::new(),
+            buffer: Vec::new(),
+            cache: Rc::new(TypeCache::new()),
+
+            #[cfg(feature = "cookies")]
+            cookies: vec![],
+
+            #[cfg(feature = "sessions")]
+            session_store: Session::default(),
+
+            #[cfg(feature = "csrf")]
+            new_csrf_token: Rc::new(RefCell::new(None)),
+        }
+    }
+}
+
 impl Request {
     /// Create a new Request from a raw one. You probably want
     /// `default()` to get an empty `Request`.
@@ -106,50 +181,64 @@ impl Request {
         body: Span,
         buffer: Vec<u8>,
     ) -> Request {
-        Request {
+        let mut req = Request {
             method,
             path,
             headers,
             body,
             buffer,
             ..Request::default()
-        }
+        };
+        req.index_headers();
+        req
     }
-    /// Produce an empty Request.
-    #[must_use]
-    pub fn default() -> Self {
-        Self {
-            remote_addr: SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 0),
-            path: Span::new(),
-            method: Span::new(),
-            body: Span::new(),
-            headers: Vec::new(),
-            args: HashMap::new(),
-            form: HashMap::new(),
-            buffer: Vec::new(),
-            cache: Rc::new(TypeCache::new()),
 
-            #[cfg(feature = "cookies")]
-            cookies: vec![],
-
-            #[cfg(feature = "sessions")]
-            session_store: Session::new(session::SECRET),
+    /// Index headers by lowercase name, so lookups don't have to
+    /// scan and lowercase every header.
+    fn index_headers(&mut self) {
+        self.header_index.clear();
+        for (i, (name, _)) in self.headers.iter().enumerate() {
+            self.header_index
+                .entry(name.in_buf(&self.buffer).to_ascii_lowercase())
+                .or_default()
+                .push(i);
         }
     }
 
+    /// Start building a Request that will be serialized into raw
+    /// HTTP and parsed, just like one coming from a client. See
+    /// [`RequestBuilder`](struct.RequestBuilder.html).
+    pub fn builder() -> RequestBuilder {
+        RequestBuilder::new()
+    }
+
     /// Read a raw HTTP request from `reader` and create an
-    /// appropriate `Request` to represent it.
-    pub fn from_reader<R: io::Read>(mut reader: R) -> Result<Request> {
+    /// appropriate `Request` to represent it. It's parsed with the
+    /// default [`Limits`](limits/struct.Limits.html), not the ones set
+    /// for your app.
+    pub fn from_reader<R: io::Read>(reader: R) -> Result<Request> {
+        Request::read(reader, &Limits::default(), |_, _| Ok(true))
+    }
+
+    /// Read a raw HTTP request from `reader` using `limits`, calling
+    /// `on_head` once the request line and headers have been read and
+    /// before the body is. The body is skipped if `on_head` returns
+    /// `false`.
+    pub(crate) fn read<R: io::Read>(
+        mut reader: R,
+        limits: &Limits,
+        on_head: impl FnOnce(&mut Request, &mut R) -> Result<bool>,
+    ) -> Result<Request> {
         let mut buffer = Vec::with_capacity(512);
         let mut read_buf = [0u8; 512];
 
         let mut req = loop {
-            let n = reader.read(&mut read_buf)?;
+            let n = read_some(&mut reader, &mut read_buf)?;
             if n == 0 {
                 return Err(Error::ConnectionClosed);
             }
             buffer.extend_from_slice(&read_buf[..n]);
-            match http_parser::parse(std::mem::take(&mut buffer))? {
+            match limits.parse(std::mem::take(&mut buffer))? {
                 http_parser::Status::Complete(req) => break req,
                 http_parser::Status::Partial(b) => {
                     let _ = mem::replace(&mut buffer, b);
@@ -157,41 +246,42 @@ impl Request {
             }
         };
 
-        if let Some(size) = req.header("Content-Length") {
-            let size = size.parse().unwrap_or(0);
-            let start = req.body.0;
+        let read_body = on_head(&mut req, &mut reader)?;
 
-            while req.buffer[start..].len() < size {
-                let n = reader.read(&mut read_buf)?;
+        // the parser has already checked Content-Length and left room
+        // for the body
+        if read_body && !req.body.is_empty() {
+            let end = req.body.1;
+            while req.buffer.len() < end {
+                let n = read_some(&mut reader, &mut read_buf)?;
                 if n == 0 {
                     break;
                 }
                 req.buffer.extend_from_slice(&read_buf[..n]);
             }
-            req.body.1 = req.body.0 + size;
             req.parse_form();
         }
 
         #[cfg(feature = "cookies")]
         {
             if let Some(cookie) = req.header("Cookie") {
-                for cookie in cookie.into_owned().split_inclusive("; ") {
-                    let cookie = Cookie::parse(cookie).map_err(|e| Error::Other(e.to_string()))?;
-                    let name = cookie.name().to_owned();
-                    let val = util::percent_decode(cookie.value()).unwrap();
-                    req.cookies.push((name.to_lowercase(), val));
-                }
+                req.cookies = util::parse_cookies(&cookie);
             }
         }
 
         #[cfg(feature = "sessions")]
         {
-            for (key, val) in &req.cookies {
-                if key.starts_with(session::PREFIX) {
-                    match req.session_store.decode(val) {
-                        Ok(v) => req.session_store.set(&key.replace(session::PREFIX, ""), &v),
-                        Err(e) => return Err(e),
-                    }
+            // A session that was tampered with or has expired is
+            // treated like no session at all. Browsers may send more
+            // than one session cookie, so use the first good one.
+            let cookies = req
+                .cookie_all(session::COOKIE)
+                .into_iter()
+                .map(String::from)
+                .collect::<Vec<_>>();
+            for val in &cookies {
+                if req.session_store.load_cookie(val).is_ok() {
+                    break;
                 }
             }
         }
@@ -205,7 +295,11 @@ impl Request {
     /// This function will error if the stream cannot be set to non-blocking (and downstream, if the request's reader blocks, or if the request cannot be parsed)
     pub fn from_stream(stream: &TcpStream) -> Result<Self> {
         stream.set_nonblocking(true)?;
-        Self::from_reader(stream)
+        let mut req = Self::from_reader(stream)?;
+        if let Ok(addr) = stream.peer_addr() {
+            crate::proxy::resolve(&mut req, addr);
+        }
+        Ok(req)
     }
 
     /// Sets the remote address of the request.
@@ -213,12 +307,116 @@ impl Request {
         self.remote_addr = socket_addr;
     }
 
-    /// Remote address of the request.
+    /// Sets the remote address and returns the new Request.
+    pub fn with_remote_addr(mut self, socket_addr: SocketAddr) -> Request {
+        self.set_remote_addr(socket_addr);
+        self
+    }
+
+    /// Remote address of the request. Behind a
+    /// [trusted proxy](proxy/index.html), this is the client's address
+    /// from the forwarded headers.
     #[must_use]
     pub fn remote_addr(&self) -> &SocketAddr {
         &self.remote_addr
     }
 
+    /// Address the connection actually came from, which is the proxy's
+    /// when there is one.
+    #[must_use]
+    pub fn peer_addr(&self) -> &SocketAddr {
+        &self.peer_addr
+    }
+
+    pub(crate) fn set_peer_addr(&mut self, socket_addr: SocketAddr) {
+        self.peer_addr = socket_addr;
+    }
+
+    /// `"https"` if a trusted proxy says the client used HTTPS,
+    /// otherwise `"http"`.
+    pub fn scheme(&self) -> &str {
+        self.scheme.as_deref().unwrap_or("http")
+    }
+
+    /// Host the client asked for: the one reported by a trusted proxy,
+    /// or else the one in an absolute-form request target, or else the
+    /// `Host` header.
+    pub fn host(&self) -> Option<Cow<'_, str>> {
+        if let Some(host) = &self.forwarded_host {
+            Some(Cow::from(host.as_str()))
+        } else if !self.authority.is_empty() {
+            Some(Cow::from(self.authority.in_buf(&self.buffer)))
+        } else {
+            self.header("Host")
+        }
+    }
+
+    /// Length of the body, according to the `Content-Length` header.
+    pub fn content_length(&self) -> Option<u64> {
+        self.header_first("Content-Length")?.trim().parse().ok()
+    }
+
+    /// Is the client waiting for a `100 Continue` before sending its
+    /// body? HTTP/1.0 clients can't ask for one.
+    pub fn expects_continue(&self) -> bool {
+        self.version == Version::HTTP11
+            && self
+                .header("Expect")
+                .is_some_and(|e| e.trim().eq_ignore_ascii_case("100-continue"))
+    }
+
+    /// HTTP version the client spoke: `HTTP/1.0` or `HTTP/1.1`.
+    pub fn version(&self) -> Version {
+        self.version
+    }
+
+    pub(crate) fn set_target(&mut self, version: Version, authority: Span) {
+        self.version = version;
+        self.authority = authority;
+    }
+
+    /// Does the client want to keep the connection open for another
+    /// request? HTTP/1.1 connections stay open unless the client sends
+    /// `Connection: close`, and HTTP/1.0 ones close unless it sends
+    /// `Connection: keep-alive`. Vial itself closes every connection
+    /// after one response either way.
+    pub fn keep_alive(&self) -> bool {
+        let has = |token: &str| {
+            self.header("Connection").is_some_and(|value| {
+                value
+                    .split(',')
+                    .any(|t| t.trim().eq_ignore_ascii_case(token))
+            })
+        };
+        match self.version {
+            Version::HTTP10 => has("keep-alive"),
+            Version::HTTP11 => !has("close"),
+        }
+    }
+
+    /// Kind of socket the request came in on: TCP or a Unix domain
+    /// socket.
+    pub fn listener(&self) -> ListenerKind {
+        self.listener
+    }
+
+    pub(crate) fn set_listener(&mut self, kind: ListenerKind, name: Option<&str>) {
+        self.listener = kind;
+        self.listener_name = name.map(|n| n.to_string());
+    }
+
+    /// Name given to the listener the request came in on with
+    /// [`Listener::named()`](struct.Listener.html#method.named), so
+    /// actions can tell an admin port from a public one.
+    pub fn listener_name(&self) -> Option<&str> {
+        self.listener_name.as_deref()
+    }
+
+    pub(crate) fn set_forwarded(&mut self, scheme: Option<String>, host: Option<String>) {
+        self.scheme = scheme;
+        self.forwarded_host = host;
+    }
+
     /// Path requested, starting with `/` and not including `?query`.
     pub fn path(&self) -> &str {
         let span = if let Some(idx) = self.full_path().find('?') {
@@ -278,7 +476,7 @@ impl Request {
     /// The `json_serde` feature must be enabled in `Cargo.toml`.
     #[cfg(feature = "json_serde")]
     pub fn json<'a, T: serde::Deserialize<'a>>(&'a self) -> serde_json::Result<T> {
-        Ok(serde_json::from_str(self.body())?)
+        serde_json::from_str(self.body())
     }
 
     /// Body of HTTP request deserialized as a JSON value.
@@ -319,32 +517,87 @@ impl Request {
         self.args.insert(name, value);
     }
 
-    #[doc(hidden)]
-    /// For testing. You should use [`header()`](#method.header) to
-    /// get a specific header from this Request.
-    pub fn headers(&self) -> &Vec<(Span, Span)> {
-        &self.headers
+    /// All the headers sent by the client as `(name, value)` pairs,
+    /// in order and with their names as sent.
+    pub fn headers(&self) -> impl ExactSizeIterator<Item = (&str, &str)> {
+        self.headers
+            .iter()
+            .map(|(n, v)| (n.in_buf(&self.buffer), v.in_buf(&self.buffer).trim_end()))
     }
 
-    /// Get a header value. `name` is case insensitive.
-    pub fn header(&self, name: &str) -> Option<Cow<str>> {
-        let name = name.to_lowercase();
-        let mut headers = self
-            .headers
-            .iter()
-            .filter(|(n, _)| n.in_buf(&self.buffer).to_ascii_lowercase() == name)
-            .map(|(_, v)| v.in_buf(&self.buffer).trim_end());
-
-        let count = headers.clone().count();
-        if count == 0 {
-            None
-        } else if count == 1 {
-            Some(Cow::from(headers.next().unwrap()))
-        } else {
-            Some(Cow::from(headers.collect::<Vec<_>>().join(", ")))
+    /// Set a header, replacing any existing headers with the same
+    /// name. `name` is case insensitive. Can be used in tests or
+    /// with `filter`.
+    pub fn set_header(&mut self, name: &str, value: &str) {
+        let buffer = &self.buffer;
+        self.headers
+            .retain(|(n, _)| !n.in_buf(buffer).eq_ignore_ascii_case(name));
+
+        let name_span = Span(self.buffer.len(), self.buffer.len() + name.len());
+        self.buffer.extend(name.as_bytes());
+        let value_span = Span(self.buffer.len(), self.buffer.len() + value.len());
+        self.buffer.extend(value.as_bytes());
+        self.headers.push((name_span, value_span));
+        self.index_headers();
+    }
+
+    /// Set a header and return the new Request.
+    pub fn with_header(mut self, name: &str, value: &str) -> Request {
+        self.set_header(name, value);
+        self
+    }
+
+    /// Get a header value. `name` is case insensitive. Repeated
+    /// headers are joined with `", "` - use
+    /// [`header_all()`](#method.header_all) to get them separately.
+    pub fn header(&self, name: &str) -> Option<Cow<'_, str>> {
+        let values = self.header_all(name);
+        match values.len() {
+            0 => None,
+            1 => Some(Cow::from(values[0])),
+            _ => Some(Cow::from(values.join(", "))),
         }
     }
 
+    /// Every value sent for a header, in order. `name` is case
+    /// insensitive.
+    pub fn header_all(&self, name: &str) -> Vec<&str> {
+        self.header_values(name).collect()
+    }
+
+    /// First value sent for a header. `name` is case insensitive.
+    fn header_first(&self, name: &str) -> Option<&str> {
+        self.header_values(name).next()
+    }
+
+    fn header_values(&self, name: &str) -> impl Iterator<Item = &str> {
+        let key = if name.bytes().any(|b| b.is_ascii_uppercase()) {
+            Cow::from(name.to_ascii_lowercase())
+        } else {
+            Cow::from(name)
+        };
+        self.header_index
+            .get(key.as_ref())
+            .into_iter()
+            .flatten()
+            .map(|&i| self.headers[i].1.in_buf(&self.buffer).trim_end())
+    }
+
+    /// The `Content-Type` header, with its parameters.
+    pub fn content_type(&self) -> Option<ContentType<'_>> {
+        ContentType::parse(self.header_first("Content-Type")?)
+    }
+
+    /// The `Authorization` header, split into scheme and credentials.
+    pub fn authorization(&self) -> Option<Authorization<'_>> {
+        Authorization::parse(self.header_first("Authorization")?)
+    }
+
+    /// The `User-Agent` header.
+    pub fn user_agent(&self) -> Option<&str> {
+        self.header_first("User-Agent")
+    }
+
     /// Was the given form value sent?
     pub fn has_form(&mut self, name: &str) -> bool {
         self.form(name).is_some()
@@ -361,6 +614,12 @@ impl Request {
         self.form.insert(name.to_string(), value.to_string());
     }
 
+    /// Set a form value and return the new Request.
+    pub fn with_form(mut self, name: &str, value: &str) -> Request {
+        self.set_form(name, value);
+        self
+    }
+
     /// Parse and decode form POST data into a Hash. Should be called
     /// when this Request is created.
     #[doc(hidden)]
@@ -399,6 +658,31 @@ impl Request {
             .next()
     }
 
+    /// Add a `name=value` pair to the ?querystring. Both are percent
+    /// encoded.
+    pub fn set_query(&mut self, name: &str, value: &str) {
+        let sep = if self.full_path().contains('?') {
+            '&'
+        } else {
+            '?'
+        };
+        let path = format!(
+            "{}{}{}={}",
+            self.full_path(),
+            sep,
+            util::percent_encode(name),
+            util::percent_encode(value)
+        );
+        self.set_path(&path);
+    }
+
+    /// Add a `name=value` pair to the ?querystring and return the
+    /// new Request.
+    pub fn with_query(mut self, name: &str, value: &str) -> Request {
+        self.set_query(name, value);
+        self
+    }
+
     /// Request's `cache()` lives for only a single Request, but can
     /// nonethenevertheless be useful to prevent looking up the same
     /// data over and over. The cache is based on the return type of
@@ -520,12 +804,49 @@ impl Request {
     }
 
     #[cfg(feature = "cookies")]
-    /// Get the value of a cookie sent by the client.
+    /// Get the value of a cookie sent by the client. Cookie names are
+    /// case sensitive, so `"SID"` and `"sid"` are different cookies.
+    /// If the client sent more than one cookie with this name, the
+    /// first one is returned.
     pub fn cookie(&self, name: &str) -> Option<&str> {
-        let name = name.to_lowercase();
         self.cookies
             .iter()
-            .find_map(|(k, v)| if k == &name { Some(v.as_ref()) } else { None })
+            .find(|(k, _)| k == name)
+            .map(|(_, v)| v.as_ref())
+    }
+
+    #[cfg(feature = "cookies")]
+    /// Every value sent for a cookie, in order. Browsers can send
+    /// several cookies with the same name when they were set for
+    /// different paths or domains, usually most specific first.
+    pub fn cookie_all(&self, name: &str) -> Vec<&str> {
+        self.cookies
+            .iter()
+            .filter(|(k, _)| k == name)
+            .map(|(_, v)| v.as_ref())
+            .collect()
+    }
+
+    #[cfg(feature = "cookies")]
+    /// All the cookies sent by the client as `(name, value)` pairs,
+    /// in order and with their names as sent.
+    pub fn cookies(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.cookies.iter().map(|(k, v)| (k.as_ref(), v.as_ref()))
+    }
+
+    #[cfg(feature = "cookies")]
+    /// Give this Request a cookie, as if the client had sent it.
+    /// Replaces any existing cookie with exactly the same name.
+    pub fn set_cookie(&mut self, name: &str, value: &str) {
+        self.cookies.retain(|(k, _)| k != name);
+        self.cookies.push((name.to_string(), value.to_string()));
+    }
+
+    #[cfg(feature = "cookies")]
+    /// Give this Request a cookie and return the new Request.
+    pub fn with_cookie(mut self, name: &str, value: &str) -> Request {
+        self.set_cookie(name, value);
+        self
     }
 
     #[cfg(feature = "sessions")]
@@ -533,4 +854,218 @@ impl Request {
     pub fn session(&self, name: &str) -> Option<&str> {
         self.session_store.get(name)
     }
+
+    #[cfg(feature = "sessions")]
+    /// The session the client sent.
+    pub(crate) fn session_store(&self) -> &Session {
+        &self.session_store
+    }
+
+    #[cfg(feature = "csrf")]
+    /// The session's CSRF token, for including in forms. Creates one
+    /// if the session doesn't have one yet. See the
+    /// [`csrf`](csrf/index.html) module.
+    pub fn csrf_token(&self) -> String {
+        if let Some(token) = self.session(csrf::SESSION_KEY) {
+            return token.to_string();
+        }
+        self.new_csrf_token
+            .borrow_mut()
+            .get_or_insert_with(csrf::new_token)
+            .clone()
+    }
+
+    #[cfg(feature = "csrf")]
+    /// A hidden `<input>` holding the CSRF token, ready to drop into
+    /// a `<form>`.
+    pub fn csrf_field(&self) -> String {
+        format!(
+            "<input type='hidden' name='{}' value='{}'/>",
+            csrf::FORM_FIELD,
+            self.csrf_token()
+        )
+    }
+
+    #[cfg(feature = "csrf")]
+    /// Shared handle to the CSRF token created during this request,
+    /// if any, so the server can save it after the action runs.
+    pub(crate) fn new_csrf_token(&self) -> Rc<RefCell<Option<String>>> {
+        self.new_csrf_token.clone()
+    }
+}
+
+/// Builds a [`Request`](struct.Request.html) by writing out raw HTTP
+/// and running it through the same parser the server uses, so
+/// headers, forms, cookies, and sessions all work the way they would
+/// for a real client. Mostly useful in tests:
+///
+/// ```rust
+/// use vial::Request;
+///
+/// let req = Request::builder()
+///     .method("POST")
+///     .path("/login")
+///     .query("next", "home")
+///     .header("Authorization", "Bearer abc123")
+///     .form("name", "Alice")
+///     .build()
+///     .unwrap();
+///
+/// assert_eq!("/login", req.path());
+/// assert_eq!(Some("home"), req.query("next"));
+/// assert_eq!("Bearer abc123", req.header("authorization").unwrap());
+/// assert_eq!(Some("Alice"), req.form("name"));
+/// ```
+#[derive(Debug, Clone)]
+pub struct RequestBuilder {
+    method: String,
+    path: String,
+    headers: Vec<(String, String)>,
+    query: Vec<(String, String)>,
+    form: Vec<(String, String)>,
+    body: Vec<u8>,
+    remote_addr: Option<SocketAddr>,
+
+    #[cfg(feature = "cookies")]
+    cookies: Vec<(String, String)>,
+}
+
+impl Default for RequestBuilder {
+    fn default() -> RequestBuilder {
+        RequestBuilder {
+            method: "GET".into(),
+            path: "/".into(),
+            headers: vec![],
+            query: vec![],
+            form: vec![],
+            body: vec![],
+            remote_addr: None,
+
+            #[cfg(feature = "cookies")]
+            cookies: vec![],
+        }
+    }
+}
+
+impl RequestBuilder {
+    /// Create a new builder for a `GET /` request.
+    pub fn new() -> RequestBuilder {
+        RequestBuilder::default()
+    }
+
+    /// HTTP Method, in "ALL-CAPS".
+    pub fn method(mut self, method: &str) -> Self {
+        self.method = method.to_string();
+        self
+    }
+
+    /// Path to request, starting with `/`. May include a `?query`.
+    pub fn path(mut self, path: &str) -> Self {
+        self.path = path.to_string();
+        self
+    }
+
+    /// Add a header.
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    /// Add a ?query value. Both `name` and `value` are percent
+    /// encoded.
+    pub fn query(mut self, name: &str, value: &str) -> Self {
+        self.query.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    /// Add a form value. The body will be sent as
+    /// `application/x-www-form-urlencoded`.
+    pub fn form(mut self, name: &str, value: &str) -> Self {
+        self.form.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    /// Set the raw body of the request.
+    pub fn body<B: AsRef<[u8]>>(mut self, body: B) -> Self {
+        self.body = body.as_ref().to_vec();
+        self
+    }
+
+    /// Remote address the request will appear to come from.
+    pub fn remote_addr(mut self, addr: SocketAddr) -> Self {
+        self.remote_addr = Some(addr);
+        self
+    }
+
+    #[cfg(feature = "cookies")]
+    /// Send a cookie along with the request.
+    pub fn cookie(mut self, name: &str, value: &str) -> Self {
+        self.cookies.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    /// Serialize into a raw HTTP request.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let encode = |pairs: &[(String, String)]| {
+            pairs
+                .iter()
+                .map(|(k, v)| format!("{}={}", util::percent_encode(k), util::percent_encode(v)))
+                .collect::<Vec<_>>()
+                .join("&")
+        };
+
+        let mut path = self.path.clone();
+        if !self.query.is_empty() {
+            path.push(if path.contains('?') { '&' } else { '?' });
+            path.push_str(&encode(&self.query));
+        }
+
+        let mut headers = self.headers.clone();
+        let mut body = self.body.clone();
+        if !self.form.is_empty() {
+            body = encode(&self.form).into_bytes();
+            headers.push((
+                "Content-Type".into(),
+                "application/x-www-form-urlencoded".into(),
+            ));
+        }
+
+        #[cfg(feature = "cookies")]
+        {
+            if !self.cookies.is_empty() {
+                headers.push((
+                    "Cookie".into(),
+                    self.cookies
+                        .iter()
+                        .map(|(k, v)| format!("{}={}", k, util::percent_encode(v)))
+                        .collect::<Vec<_>>()
+                        .join("; "),
+                ));
+            }
+        }
+
+        if !body.is_empty() {
+            headers.push(("Content-Length".into(), body.len().to_string()));
+        }
+
+        let mut raw = format!("{} {} HTTP/1.1\r\n", self.method, path);
+        for (name, val) in headers {
+            raw.push_str(&format!("{}: {}\r\n", name, val));
+        }
+        raw.push_str("\r\n");
+
+        let mut raw = raw.into_bytes();
+        raw.extend(body);
+        raw
+    }
+
+    /// Serialize and parse the request, returning an error if the
+    /// parser rejects it.
+    pub fn build(self) -> Result<Request> {
+        let mut req = Request::from_reader(io::Cursor::new(self.to_bytes()))?;
+        if let Some(addr) = self.remote_addr {
+            req.set_remote_addr(addr);
+        }
+        Ok(req)
+    }
 }
@@ -1,20 +1,35 @@
 use {
-    crate::{asset, util, Result},
+    crate::{asset, mime, util, HeaderMap, Result, Version},
     std::{
-        collections::HashMap,
         error, fmt, fs,
-        io::{self, BufReader},
+        io::{self, BufReader, Read},
+        str,
     },
 };
 
+#[cfg(feature = "cookies")]
+use {
+    crate::{
+        cookie::{self, Cookie},
+        Error,
+    },
+    std::collections::HashMap,
+};
+
 #[cfg(feature = "sessions")]
 use crate::session::{self, Session};
 
+#[cfg(feature = "websocket")]
+use crate::websocket::{self, WebSocket};
+
+use crate::sse::{self, EventSender};
+
 /// Response Body. Will be either a `String` or `io::Read`, like from
-/// a File.
-enum Body {
+/// a File. `Bytes` is a `Reader` that has been read into memory.
+pub(crate) enum Body {
     None,
     String(String),
+    Bytes(Vec<u8>),
     Reader(Box<dyn io::Read>),
 }
 
@@ -22,10 +37,19 @@ impl Body {
     /// Body as a string. Always empty if this is a `Reader`,
     /// otherwise we'd have to consume the stream.
     fn as_str(&self) -> &str {
-        if let Body::String(s) = self {
-            s.as_ref()
-        } else {
-            ""
+        match self {
+            Body::String(s) => s.as_ref(),
+            Body::Bytes(b) => str::from_utf8(b).unwrap_or(""),
+            _ => "",
+        }
+    }
+
+    /// Body as raw bytes. Always empty if this is a `Reader`.
+    fn as_bytes(&self) -> &[u8] {
+        match self {
+            Body::String(s) => s.as_bytes(),
+            Body::Bytes(b) => b,
+            _ => &[],
         }
     }
 }
@@ -34,6 +58,7 @@ impl fmt::Display for Body {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Body::String(s) => write!(f, "{}", s),
+            Body::Bytes(b) => write!(f, "{}", String::from_utf8_lossy(b)),
             Body::Reader(..) => write!(f, "(io::Read)"),
             _ => write!(f, "None"),
         }
@@ -66,18 +91,28 @@ pub struct Response {
     code: usize,
 
     /// The headers we're sending back.
-    headers: HashMap<String, String>,
+    headers: HeaderMap,
 
     /// Response body.
     body: Body,
 
     #[cfg(feature = "cookies")]
     /// Cookies to set.
-    cookies: HashMap<String, String>,
+    cookies: HashMap<String, Cookie>,
 
     #[cfg(feature = "sessions")]
     /// Session store.,
     session_store: Session,
+
+    #[cfg(feature = "websocket")]
+    /// Takes over the connection if this is a WebSocket upgrade.
+    websocket: Option<websocket::Handler>,
+
+    /// Produces the body if this is a stream of Server-Sent Events.
+    events: Option<sse::Handler>,
+
+    /// HTTP version to answer in, matching the request's.
+    version: Version,
 }
 
 impl PartialEq for Response {
@@ -85,7 +120,7 @@ impl PartialEq for Response {
         self.code == other.code
             && self.headers == other.headers
             && self.content_type() == other.content_type()
-            && self.body.as_str() == other.body.as_str()
+            && self.body.as_bytes() == other.body.as_bytes()
     }
 }
 
@@ -107,12 +142,9 @@ impl fmt::Display for Response {
 
 impl Default for Response {
     fn default() -> Response {
-        let mut headers = HashMap::new();
-        headers.insert(
-            "Content-Type".to_lowercase(),
-            "text/html; charset=utf8".into(),
-        );
-        headers.insert("Content-Length".to_lowercase(), "0".into());
+        let mut headers = HeaderMap::new();
+        let _ = headers.set("Content-Type", "text/html; charset=utf8");
+        let _ = headers.set("Content-Length", "0");
 
         Response {
             code: 200,
@@ -123,7 +155,14 @@ impl Default for Response {
             cookies: HashMap::new(),
 
             #[cfg(feature = "sessions")]
-            session_store: Session::new(session::SECRET),
+            session_store: Session::default(),
+
+            #[cfg(feature = "websocket")]
+            websocket: None,
+
+            events: None,
+
+            version: Version::default(),
         }
     }
 }
@@ -139,6 +178,23 @@ impl Response {
         self.code
     }
 
+    /// HTTP version this response is sent with. Vial answers in the
+    /// version the request was made in.
+    pub fn version(&self) -> Version {
+        self.version
+    }
+
+    /// Send this response with a different HTTP version.
+    pub fn set_version(&mut self, version: Version) {
+        self.version = version;
+    }
+
+    /// Send this response with a different HTTP version and return it.
+    pub fn with_version(mut self, version: Version) -> Response {
+        self.set_version(version);
+        self
+    }
+
     /// Either the inferred or user-set Content-Type for this Response.
     pub fn content_type(&self) -> &str {
         self.header("Content-Type").unwrap_or("")
@@ -149,37 +205,118 @@ impl Response {
         self.body.as_str()
     }
 
+    /// Response body as raw bytes. Empty if the body is an
+    /// `io::Read` that hasn't been read yet, which is the case for
+    /// files and assets unless the `Response` came from a
+    /// [`test::Client`](test/struct.Client.html).
+    pub fn body_bytes(&self) -> &[u8] {
+        self.body.as_bytes()
+    }
+
+    /// Body of the response deserialized as a JSON value.
+    ///
+    /// The `json_serde` feature must be enabled in `Cargo.toml`.
+    #[cfg(feature = "json_serde")]
+    pub fn json<'a, T: serde::Deserialize<'a>>(&'a self) -> serde_json::Result<T> {
+        serde_json::from_slice(self.body_bytes())
+    }
+
+    /// Body of the response deserialized as a JSON value.
+    ///
+    /// The `json_nano` feature must be enabled in `Cargo.toml`.
+    #[cfg(feature = "json_nano")]
+    pub fn json<T: nanoserde::DeJson>(&self) -> core::result::Result<T, nanoserde::DeJsonErr> {
+        T::deserialize_json(self.body())
+    }
+
+    /// Read an `io::Read` body into memory so it can be inspected
+    /// with [`body()`](#method.body) and friends.
+    pub(crate) fn buffer_body(&mut self) -> Result<()> {
+        if let Body::Reader(reader) = &mut self.body {
+            let mut buf = vec![];
+            reader.read_to_end(&mut buf)?;
+            self.body = Body::Bytes(buf);
+        }
+        Ok(())
+    }
+
     /// Take a peek at all the headers for this response.
-    pub fn headers(&self) -> &HashMap<String, String> {
+    pub fn headers(&self) -> &HeaderMap {
         &self.headers
     }
 
-    /// Get an individual header. `name` is case insensitive.
+    /// All the headers for this response, for when you'd like to
+    /// know whether setting one failed.
+    pub fn headers_mut(&mut self) -> &mut HeaderMap {
+        &mut self.headers
+    }
+
+    /// Get an individual header. `name` is case insensitive. If the
+    /// header was set more than once, this is the first value.
     pub fn header(&self, name: &str) -> Option<&str> {
-        self.headers.get(&name.to_lowercase()).map(|h| h.as_ref())
+        self.headers.get(name)
+    }
+
+    /// Every value set for a header, in order. `name` is case
+    /// insensitive.
+    pub fn header_all(&self, name: &str) -> Vec<&str> {
+        self.headers.get_all(name)
     }
 
-    /// Set an individual header.
+    /// Set an individual header, replacing any values it already
+    /// had. Headers with invalid names or values, like ones with a
+    /// line break in them, are logged and left out.
     pub fn set_header(&mut self, name: &str, value: &str) {
-        self.headers.insert(name.to_lowercase(), value.to_string());
+        if let Err(e) = self.headers.set(name, value) {
+            eprintln!("!! {}: {}", e, name);
+        }
+    }
+
+    /// Add another value for a header, keeping any it already had.
+    /// Invalid headers are logged and left out, like
+    /// [`set_header()`](#method.set_header).
+    pub fn append_header(&mut self, name: &str, value: &str) {
+        if let Err(e) = self.headers.append(name, value) {
+            eprintln!("!! {}: {}", e, name);
+        }
+    }
+
+    /// Remove every value for a header.
+    pub fn remove_header(&mut self, name: &str) {
+        self.headers.remove(name);
     }
 
     #[cfg(feature = "cookies")]
-    /// Get an individual cookie. `name` is case insensitive.
+    /// Get an individual cookie. `name` is case sensitive, like it is
+    /// for browsers.
     pub fn cookie(&self, name: &str) -> Option<&str> {
-        self.cookies.get(&name.to_lowercase()).map(|h| h.as_ref())
+        self.cookies.get(name).map(|c| c.value())
+    }
+
+    #[cfg(feature = "cookies")]
+    /// All the cookies this response will set. Removed cookies have
+    /// an empty value.
+    pub(crate) fn cookies(&self) -> &HashMap<String, Cookie> {
+        &self.cookies
     }
 
     #[cfg(feature = "cookies")]
-    /// Set a cookie.
-    pub fn set_cookie(&mut self, name: &str, value: &str) {
-        self.cookies.insert(name.to_lowercase(), value.into());
+    /// Set a cookie. Takes either a plain value or a
+    /// [`Cookie`](struct.Cookie.html) with attributes. Names that
+    /// aren't valid, like ones with a line break in them, are logged
+    /// and left out.
+    pub fn set_cookie<C: Into<Cookie>>(&mut self, name: &str, cookie: C) {
+        if !cookie::is_valid_name(name) {
+            eprintln!("!! {}: {}", Error::InvalidCookieName, name);
+            return;
+        }
+        self.cookies.insert(name.to_string(), cookie.into());
     }
 
     #[cfg(feature = "cookies")]
     /// Remove a cookie from the client.
     pub fn remove_cookie(&mut self, name: &str) {
-        self.cookies.insert(name.to_lowercase(), "".into());
+        self.set_cookie(name, Cookie::default());
     }
 
     #[cfg(feature = "sessions")]
@@ -189,22 +326,86 @@ impl Response {
     }
 
     #[cfg(feature = "sessions")]
-    /// Set a session item.
+    /// Set a session item. Items the client already had in its
+    /// session are kept.
     pub fn set_session(&mut self, name: &str, value: &str) {
         self.session_store.set(name, value);
-        self.set_cookie(&self.session_key(name), &self.session_store.encode(value));
     }
 
     #[cfg(feature = "sessions")]
     /// Remove a session item.
     pub fn remove_session(&mut self, name: &str) {
         self.session_store.remove(name);
-        self.remove_cookie(&self.session_key(name));
     }
 
     #[cfg(feature = "sessions")]
-    fn session_key(&self, key: &str) -> String {
-        format!("{}{}", session::PREFIX, key.to_lowercase())
+    /// Remove every item from the client's session.
+    pub fn clear_session(&mut self) {
+        self.session_store.clear();
+    }
+
+    #[cfg(feature = "sessions")]
+    /// Send the client's session back with a fresh expiration time,
+    /// even if nothing in it changed.
+    pub fn regenerate_session(&mut self) {
+        self.session_store.regenerate();
+    }
+
+    #[cfg(feature = "sessions")]
+    /// Merge the changes made to this response's session into the
+    /// session the client sent and set the session cookie, if
+    /// anything changed.
+    pub(crate) fn save_session(&mut self, base: Option<&Session>) {
+        if !self.session_store.is_dirty() {
+            return;
+        }
+        if let Some(base) = base {
+            self.session_store.merge(base);
+        }
+        match self.session_store.save() {
+            Ok(Some(value)) => self.set_cookie(session::COOKIE, session::cookie(&value)),
+            Ok(None) => self.set_cookie(session::COOKIE, session::cookie("")),
+            Err(e) => eprintln!("!! {}: {}", e, session::COOKIE),
+        }
+        self.session_store.mark_saved();
+    }
+
+    /// Respond with a stream of Server-Sent Events. `handler` runs on
+    /// its own thread and sends events for as long as it likes; the
+    /// response ends when it returns. See the [`sse`](sse/index.html)
+    /// module.
+    pub fn sse<F: FnOnce(EventSender) + Send + 'static>(handler: F) -> Response {
+        let mut res = Response::default()
+            .with_header("Content-Type", "text/event-stream")
+            .with_header("Cache-Control", "no-cache")
+            .with_header("X-Accel-Buffering", "no");
+        res.remove_header("Content-Length");
+        res.events = Some(Box::new(handler));
+        res
+    }
+
+    /// Take the Server-Sent Events handler out of this response, if
+    /// it has one.
+    pub(crate) fn take_events(&mut self) -> Option<sse::Handler> {
+        self.events.take()
+    }
+
+    #[cfg(feature = "websocket")]
+    /// Accept a WebSocket upgrade request. Once the handshake is
+    /// done, `handler` is run on its own thread with the connection.
+    /// Requests that aren't WebSocket upgrades get a `400 Bad
+    /// Request` instead. See the [`websocket`](websocket/index.html)
+    /// module.
+    pub fn websocket<F: FnOnce(WebSocket) + Send + 'static>(handler: F) -> Response {
+        let mut res = Response::from(101);
+        res.websocket = Some(Box::new(handler));
+        res
+    }
+
+    #[cfg(feature = "websocket")]
+    /// Take the WebSocket handler out of this response, if it has one.
+    pub(crate) fn take_websocket(&mut self) -> Option<websocket::Handler> {
+        self.websocket.take()
     }
 
     /// Convert into a Response.
@@ -244,8 +445,8 @@ impl Response {
     #[cfg(feature = "cookies")]
     /// Creates a new Response and sets the given cookie, in
     /// addition to the defaults.
-    pub fn from_cookie(name: &str, value: &str) -> Response {
-        Response::default().with_cookie(name, value)
+    pub fn from_cookie<C: Into<Cookie>>(name: &str, cookie: C) -> Response {
+        Response::default().with_cookie(name, cookie)
     }
 
     #[cfg(feature = "sessions")]
@@ -274,18 +475,30 @@ impl Response {
     pub fn with_code(mut self, code: usize) -> Response {
         self.code = code;
         match code {
+            400 => self.with_body("400 Bad Request"),
             404 => self.with_body("404 Not Found"),
+            408 => self.with_body("408 Request Timeout"),
+            413 => self.with_body("413 Payload Too Large"),
+            417 => self.with_body("417 Expectation Failed"),
+            429 => self.with_body("429 Too Many Requests"),
+            431 => self.with_body("431 Request Header Fields Too Large"),
             500 => self.with_body("500 Internal Server Error"),
+            503 => self.with_body("503 Service Unavailable"),
             _ => self,
         }
     }
 
     /// Body builder. Returns a Response with the given body.
     pub fn with_body<S: AsRef<str>>(mut self, body: S) -> Response {
+        self.set_body(body);
+        self
+    }
+
+    /// Set the body of this Response.
+    pub fn set_body<S: AsRef<str>>(&mut self, body: S) {
         let body = body.as_ref();
         self.body = Body::String(body.to_string());
         self.set_header("Content-Length", &body.len().to_string());
-        self
     }
 
     /// Returns an `application/json` Response with a body serialized as JSON
@@ -326,7 +539,8 @@ impl Response {
     }
 
     /// Uses an asset for the given body and sets the `Content-Type`
-    /// header based on the file's extension.
+    /// header based on the file's extension. See the
+    /// [`mime`](mime/index.html) module to add your own.
     ///
     /// See the [`asset`](asset/index.html) module for more
     /// information on using assets.
@@ -336,7 +550,8 @@ impl Response {
                 if asset::is_bundled() {
                     if let Some(reader) = asset::as_reader(&path) {
                         self.set_header("ETag", asset::etag(&path).as_ref());
-                        self.set_header("Content-Type", util::content_type(&path));
+                        let content_type = mime::for_file(&path, || asset::as_reader(&path));
+                        self.set_header("Content-Type", &content_type);
                         self.set_header("Content-Length", &asset::size(&path).to_string());
                         return self.with_reader(reader);
                     }
@@ -349,7 +564,8 @@ impl Response {
     }
 
     /// Sets this Response's body to the body of the given file and
-    /// sets the `Content-Type` header based on the file's extension.
+    /// sets the `Content-Type` header based on the file's extension,
+    /// like [`with_asset()`](#method.with_asset).
     pub fn with_file(mut self, path: &str) -> Response {
         if !std::path::Path::new(path).exists() {
             return Response::from(404);
@@ -357,7 +573,8 @@ impl Response {
         match fs::File::open(path) {
             Ok(file) => {
                 self.set_header("ETag", asset::etag(path).as_ref());
-                self.set_header("Content-Type", util::content_type(path));
+                let content_type = mime::for_file(path, || fs::File::open(path).ok());
+                self.set_header("Content-Type", &content_type);
                 self.set_header("Content-Length", &util::file_size(path).to_string());
                 self.with_reader(Box::new(BufReader::new(file)))
             }
@@ -369,7 +586,7 @@ impl Response {
     /// Sets the response code to 500 and the body to the error's text.
     pub fn with_error<E: error::Error>(self, err: E) -> Response {
         self.with_code(500)
-            .with_body(&format!("<h1>500 Internal Error</h1><pre>{:?}", err))
+            .with_body(format!("<h1>500 Internal Error</h1><pre>{:?}", err))
     }
 
     /// Returns a Response with the given header set to the value.
@@ -378,10 +595,25 @@ impl Response {
         self
     }
 
+    /// Returns a Response with another value added for the given
+    /// header.
+    pub fn with_appended_header(mut self, key: &str, value: &str) -> Response {
+        self.append_header(key, value);
+        self
+    }
+
+    /// Returns a Response without the given header.
+    pub fn without_header(mut self, key: &str) -> Response {
+        self.remove_header(key);
+        self
+    }
+
     #[cfg(feature = "cookies")]
     /// Returns a Response with the given cookie set to the value.
-    pub fn with_cookie(mut self, key: &str, value: &str) -> Response {
-        self.set_cookie(key, value);
+    /// Takes either a plain value or a [`Cookie`](struct.Cookie.html)
+    /// with attributes.
+    pub fn with_cookie<C: Into<Cookie>>(mut self, key: &str, cookie: C) -> Response {
+        self.set_cookie(key, cookie);
         self
     }
 
@@ -406,10 +638,19 @@ impl Response {
         self
     }
 
+    #[cfg(feature = "sessions")]
+    /// Returns a Response that removes every item in the client's
+    /// session.
+    pub fn with_cleared_session(mut self) -> Response {
+        self.clear_session();
+        self
+    }
+
     /// Length of the body.
     pub fn len(&self) -> usize {
         match &self.body {
             Body::String(s) => s.len(),
+            Body::Bytes(b) => b.len(),
             Body::Reader(..) => self
                 .header("Content-Length")
                 .unwrap_or("0")
@@ -426,64 +667,72 @@ impl Response {
 
     /// Returns a 302 redirect to the given URL.
     pub fn redirect_to<U: AsRef<str>>(url: U) -> Response {
-        Response::from(302).with_header("location", url.as_ref())
+        Response::from(302).with_header("Location", url.as_ref())
     }
 
     /// Writes this response to a stream.
     pub fn write<W: io::Write>(self, mut w: W) -> Result<()> {
+        let body = self.write_head(&mut w)?;
+
+        match body {
+            Body::Reader(mut reader) => {
+                io::copy(&mut reader, &mut w)?;
+            }
+            Body::String(s) => {
+                w.write_all(s.as_bytes())?;
+            }
+            Body::Bytes(b) => {
+                w.write_all(&b)?;
+            }
+            _ => {}
+        }
+
+        w.flush()?;
+
+        Ok(())
+    }
+
+    /// Writes the status line and headers of this response to a
+    /// stream, returning the body so it can be sent next.
+    pub(crate) fn write_head<W: io::Write>(
+        #[allow(unused_mut)] mut self,
+        w: &mut W,
+    ) -> Result<Body> {
+        #[cfg(feature = "sessions")]
+        self.save_session(None);
+
         // gross - move into print_headers or something
         let mut header = format!(
-            "HTTP/1.1 {} OK\r\nServer: ~ vial {} ~\r\nDate: {}\r\nConnection: close\r\n",
+            "{} {} {}\r\nServer: ~ vial {} ~\r\nDate: {}\r\nConnection: close\r\n",
+            self.version,
             self.code,
+            util::reason_phrase(self.code),
             crate::VERSION,
             util::http_current_date(),
         );
 
         // TODO check for content-type, date, etc
-        header.push_str(
-            &self
-                .headers
-                .iter()
-                .map(|(key, val)| format!("{}: {}", key, val))
-                .collect::<Vec<_>>()
-                .join("\r\n"),
-        );
-
-        if !header.ends_with("\r\n") {
+        for (name, value) in self.headers.iter() {
+            header.push_str(name);
+            header.push_str(": ");
+            header.push_str(value);
             header.push_str("\r\n");
         }
 
         #[cfg(feature = "cookies")]
         {
-            for (name, val) in self.cookies {
+            for (name, cookie) in self.cookies {
                 header.push_str("Set-Cookie: ");
-                header.push_str(&name);
-                header.push('=');
-                if val.is_empty() {
-                    header.push_str("; Expires=Thu, 01 Jan 1970 00:00:00 GMT");
-                } else {
-                    header.push_str(&val);
-                }
+                header.push_str(&cookie.to_header(&name));
                 header.push_str("\r\n");
             }
         }
 
         header.push_str("\r\n");
         w.write_all(header.as_bytes())?;
-
-        match self.body {
-            Body::Reader(mut reader) => {
-                io::copy(&mut reader, &mut w)?;
-            }
-            Body::String(s) => {
-                w.write_all(s.as_bytes())?;
-            }
-            _ => {}
-        }
-
         w.flush()?;
 
-        Ok(())
+        Ok(self.body)
     }
 }
 
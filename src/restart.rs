@@ -0,0 +1,226 @@
+//! Restarting without dropping connections, for deploying a new
+//! binary in place of the running one.
+//!
+//! Call [`enable()`](fn.enable.html) before your app starts:
+//!
+//! ```no_run
+//! use vial::prelude::*;
+//!
+//! routes! {
+//!     GET "/" => |_| "Hello, world!";
+//! }
+//!
+//! fn main() {
+//!     vial::restart::enable();
+//!     run!().unwrap();
+//! }
+//! ```
+//!
+//! Then send the running process `SIGHUP` or `SIGUSR2`, like with
+//! `kill -HUP <pid>`. Vial starts the binary again, with the same
+//! arguments, and hands it the sockets it's listening on. Once the
+//! new process is accepting connections, the old one stops accepting,
+//! finishes the requests it's already working on, and `run!()`
+//! returns `Ok(())`. If the new process fails to start, the old one
+//! carries on as if nothing happened.
+//!
+//! WebSocket connections and
+//! [event streams](../struct.Response.html#method.sse) aren't drained.
+//! They can stay open forever, so the old process doesn't wait for
+//! them, and they're closed when it exits. Clients should reconnect,
+//! which `EventSource` does on its own.
+//!
+//! The new process picks the sockets up when it asks for the same
+//! addresses, so nothing about your `main()` needs to change. Any
+//! sockets it doesn't ask for are closed.
+//!
+//! Only available on Unix.
+use {
+    crate::{Error, Listener, Result},
+    std::{
+        env,
+        io::{self, Read, Write},
+        os::unix::{
+            io::{AsRawFd, FromRawFd, RawFd},
+            net::UnixStream,
+            process::CommandExt,
+        },
+        process::Command,
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            Mutex, RwLock,
+        },
+        time::Duration,
+    },
+};
+
+/// Sockets passed on by the old process, as a comma separated list
+/// of file descriptors.
+const LISTEN_FDS_VAR: &str = "VIAL_LISTEN_FDS";
+
+/// Socket the new process tells the old one it's ready on.
+const READY_FD_VAR: &str = "VIAL_READY_FD";
+
+/// How long the new process gets to start accepting connections.
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(30);
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static REQUESTED: AtomicBool = AtomicBool::new(false);
+static DRAIN_TIMEOUT: RwLock<Duration> = RwLock::new(Duration::from_secs(30));
+
+/// Sockets passed on by the old process, loaded the first time
+/// they're asked for.
+static INHERITED: Mutex<Option<Vec<Listener>>> = Mutex::new(None);
+
+/// Restart on `SIGHUP` or `SIGUSR2`. Should be called before your
+/// app starts.
+pub fn enable() {
+    ENABLED.store(true, Ordering::SeqCst);
+    for signal in [libc::SIGHUP, libc::SIGUSR2] {
+        // Safety: the handler only stores to an atomic.
+        unsafe {
+            let mut action: libc::sigaction = std::mem::zeroed();
+            action.sa_sigaction = on_signal as extern "C" fn(libc::c_int) as libc::sighandler_t;
+            action.sa_flags = libc::SA_RESTART;
+            libc::sigemptyset(&mut action.sa_mask);
+            libc::sigaction(signal, &action, std::ptr::null_mut());
+        }
+    }
+}
+
+/// Is restarting turned on?
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::SeqCst)
+}
+
+/// How long the old process waits for its requests to finish before
+/// giving up on them. Defaults to 30 seconds. WebSocket connections
+/// and event streams aren't waited for.
+pub fn set_drain_timeout(timeout: Duration) {
+    *DRAIN_TIMEOUT.write().unwrap() = timeout;
+}
+
+pub(crate) fn drain_timeout() -> Duration {
+    *DRAIN_TIMEOUT.read().unwrap()
+}
+
+extern "C" fn on_signal(_: libc::c_int) {
+    REQUESTED.store(true, Ordering::SeqCst);
+}
+
+/// Has a restart been asked for since the last time we checked?
+pub(crate) fn requested() -> bool {
+    REQUESTED.swap(false, Ordering::SeqCst)
+}
+
+/// Take the socket passed on by the old process that `matches`.
+pub(crate) fn take<F: Fn(&Listener) -> bool>(matches: F) -> Option<Listener> {
+    let mut inherited = INHERITED.lock().unwrap();
+    let listeners = inherited.get_or_insert_with(load);
+    let i = listeners.iter().position(matches)?;
+    Some(listeners.remove(i))
+}
+
+/// Close any sockets passed on by the old process that weren't used.
+pub(crate) fn close_unused() {
+    INHERITED.lock().unwrap().get_or_insert_with(load).clear();
+}
+
+/// Pick up the sockets listed in `VIAL_LISTEN_FDS`.
+fn load() -> Vec<Listener> {
+    let fds = match env::var(LISTEN_FDS_VAR) {
+        Ok(fds) => fds,
+        Err(_) => return vec![],
+    };
+    env::remove_var(LISTEN_FDS_VAR);
+    fds.split(',')
+        .filter_map(|fd| fd.trim().parse::<RawFd>().ok())
+        .filter(|&fd| is_socket(fd))
+        // Safety: the old process handed these to us and nothing else
+        // in this process knows about them.
+        .map(|fd| unsafe { Listener::from_fd(fd) })
+        .collect()
+}
+
+/// Is `fd` an open socket?
+fn is_socket(fd: RawFd) -> bool {
+    // Safety: fstat only writes to the struct we give it.
+    unsafe {
+        let mut stat: libc::stat = std::mem::zeroed();
+        libc::fstat(fd, &mut stat) == 0 && stat.st_mode & libc::S_IFMT == libc::S_IFSOCK
+    }
+}
+
+/// Tell the old process we're accepting connections, if there is one.
+pub(crate) fn notify_ready() {
+    let fd = match env::var(READY_FD_VAR) {
+        Ok(fd) => fd,
+        Err(_) => return,
+    };
+    env::remove_var(READY_FD_VAR);
+    if let Ok(fd) = fd.trim().parse::<RawFd>() {
+        if is_socket(fd) {
+            // Safety: the old process handed this to us.
+            let mut ready = unsafe { UnixStream::from_raw_fd(fd) };
+            if let Err(e) = ready.write_all(b"1") {
+                eprintln!("!! {}", e);
+            }
+        }
+    }
+}
+
+/// Start the binary again with the same arguments, handing it the
+/// sockets in `fds`, and wait for it to start accepting connections.
+/// Returns the new process's ID.
+pub(crate) fn spawn(fds: &[RawFd]) -> Result<u32> {
+    let (mut ready, theirs) = UnixStream::pair()?;
+    let theirs_fd = theirs.as_raw_fd();
+    let list = fds
+        .iter()
+        .map(|fd| fd.to_string())
+        .collect::<Vec<_>>()
+        .join(",");
+
+    let mut passed = fds.to_vec();
+    passed.push(theirs_fd);
+    let mut command = Command::new(current_exe()?);
+    command
+        .args(env::args_os().skip(1))
+        .env(LISTEN_FDS_VAR, list)
+        .env(READY_FD_VAR, theirs_fd.to_string());
+    // Safety: only calls fcntl(), which is safe to call after fork().
+    unsafe {
+        command.pre_exec(move || {
+            for &fd in &passed {
+                let flags = libc::fcntl(fd, libc::F_GETFD);
+                if flags < 0 || libc::fcntl(fd, libc::F_SETFD, flags & !libc::FD_CLOEXEC) < 0 {
+                    return Err(io::Error::last_os_error());
+                }
+            }
+            Ok(())
+        });
+    }
+    let mut child = command.spawn()?;
+    drop(theirs);
+
+    ready.set_read_timeout(Some(STARTUP_TIMEOUT))?;
+    let mut byte = [0u8; 1];
+    match ready.read(&mut byte) {
+        Ok(1) => Ok(child.id()),
+        _ => {
+            let _ = child.kill();
+            let _ = child.wait();
+            Err(Error::Other("new process didn't start".into()))
+        }
+    }
+}
+
+/// Path to the running binary. On Linux it's marked as deleted once a
+/// deploy has replaced it, but the new binary is at the same path.
+fn current_exe() -> io::Result<std::path::PathBuf> {
+    let exe = env::current_exe()?;
+    match exe.to_str().and_then(|s| s.strip_suffix(" (deleted)")) {
+        Some(path) => Ok(path.into()),
+        None => Ok(exe),
+    }
+}
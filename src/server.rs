@@ -1,23 +1,51 @@
 use {
-    crate::{asset, Request, Response, Result, Router},
+    crate::{
+        asset, cors,
+        limits::{self, Clients, Limits, Timed},
+        listener::{Acceptor, Bind, Options, Stream},
+        proxy, sse, Error, Request, Response, Result, Router, Version,
+    },
     std::{
         io::Write,
-        net::{TcpListener, TcpStream, ToSocketAddrs},
-        sync::{Arc, Mutex},
+        sync::{atomic::AtomicBool, mpsc, Arc, Mutex},
+        thread,
     },
     threadpool::ThreadPool,
 };
 
+#[cfg(unix)]
+use {
+    crate::restart,
+    std::{
+        os::unix::io::{AsRawFd, RawFd},
+        sync::{atomic::Ordering, mpsc::RecvTimeoutError},
+        time::{Duration, Instant},
+    },
+};
+
+#[cfg(feature = "websocket")]
+use crate::websocket;
+
 const MAX_CONNECTIONS: usize = 10;
 
+/// Methods listed in response to `OPTIONS *`.
+const ALLOWED_METHODS: &str = "GET, HEAD, POST, PUT, PATCH, DELETE, OPTIONS";
+
+/// How often to check for restart signals and finished requests.
+#[cfg(unix)]
+const RESTART_CHECK_INTERVAL: Duration = Duration::from_millis(100);
+
 /// Starts a new Vial server. Should always be invoked via the
 /// [`vial::run!()`](macro.run.html) macro, since there is some setup
 /// that needs to happen.
+///
+/// `addr` can be anything that converts to a socket address, like
+/// `"0.0.0.0:7667"`, a [`Listener`](struct.Listener.html), or a `Vec`
+/// of listeners to serve the same app on all at once.
 #[doc(hidden)]
-pub fn run<T: ToSocketAddrs>(addr: T, router: Router, banner: Option<&str>) -> Result<()> {
+pub fn run<T: Bind>(addr: T, router: Router, banner: Option<&str>) -> Result<()> {
     let pool = ThreadPool::new(MAX_CONNECTIONS);
-    let listener = TcpListener::bind(&addr)?;
-    let addr = listener.local_addr()?;
+    let listeners = addr.bind()?;
     let server = Arc::new(Server::new(router));
 
     #[cfg(feature = "state")]
@@ -25,41 +53,220 @@ pub fn run<T: ToSocketAddrs>(addr: T, router: Router, banner: Option<&str>) -> R
 
     if let Some(banner) = banner {
         if !banner.is_empty() {
-            println!("{}", banner.replace("{}", &format!("http://{}", addr)));
+            let addrs: Vec<_> = listeners.iter().map(|l| l.to_string()).collect();
+            println!("{}", banner.replace("{}", &addrs.join(", ")));
         }
     } else {
-        println!("~ vial running at http://{}", addr);
+        for listener in &listeners {
+            println!("~ vial running at {}", listener);
+        }
+    }
+
+    #[cfg(unix)]
+    let (restartable, fds) = {
+        restart::close_unused();
+        let fds: Vec<RawFd> = listeners.iter().map(|l| l.as_raw_fd()).collect();
+        (restart::is_enabled(), fds)
+    };
+    #[cfg(not(unix))]
+    let restartable = false;
+
+    // every listener gets its own accept thread, and the first one
+    // to fail brings the server down
+    let clients = Arc::new(Clients::default());
+    let stop = Arc::new(AtomicBool::new(false));
+    let (errors, failed) = mpsc::channel();
+    for listener in listeners {
+        let (acceptor, options) = listener.into_parts();
+        if restartable {
+            acceptor.set_nonblocking()?;
+        }
+        let (server, pool, clients, stop, errors) = (
+            server.clone(),
+            pool.clone(),
+            clients.clone(),
+            stop.clone(),
+            errors.clone(),
+        );
+        thread::spawn(move || {
+            let res = accept_loop(acceptor, Arc::new(options), server, pool, clients, &stop);
+            let _ = errors.send(res);
+        });
     }
+    drop(errors);
 
-    for stream in listener.incoming() {
+    #[cfg(unix)]
+    {
+        restart::notify_ready();
+        if restartable {
+            return serve_until_restart(failed, &stop, &fds, &pool);
+        }
+    }
+
+    match failed.recv() {
+        Ok(res) => res,
+        Err(_) => Ok(()),
+    }
+}
+
+/// Serve until a restart hands our sockets to a new process, then
+/// finish the requests that are already in progress.
+#[cfg(unix)]
+fn serve_until_restart(
+    failed: mpsc::Receiver<Result<()>>,
+    stop: &AtomicBool,
+    fds: &[RawFd],
+    pool: &ThreadPool,
+) -> Result<()> {
+    let mut accepting = fds.len();
+    while accepting > 0 {
+        match failed.recv_timeout(RESTART_CHECK_INTERVAL) {
+            Ok(Err(e)) => return Err(e),
+            Ok(Ok(())) => accepting -= 1,
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+        if restart::requested() && !stop.load(Ordering::SeqCst) {
+            match restart::spawn(fds) {
+                Ok(pid) => {
+                    println!("~ vial restarted as process {}", pid);
+                    stop.store(true, Ordering::SeqCst);
+                }
+                Err(e) => eprintln!("!! restart failed: {}", e),
+            }
+        }
+    }
+
+    // WebSockets and event streams have left the pool by now and are
+    // closed when the process exits, not drained
+    let deadline = Instant::now() + restart::drain_timeout();
+    while pool.active_count() + pool.queued_count() > 0 && Instant::now() < deadline {
+        thread::sleep(RESTART_CHECK_INTERVAL);
+    }
+    Ok(())
+}
+
+/// Accept connections on one listener until it fails or is told to
+/// `stop`.
+fn accept_loop(
+    acceptor: Acceptor,
+    options: Arc<Options>,
+    server: Arc<Server>,
+    pool: ThreadPool,
+    clients: Arc<Clients>,
+    stop: &AtomicBool,
+) -> Result<()> {
+    loop {
         let server = server.clone();
-        let stream = stream?;
+        let options = options.clone();
+        let stream = match acceptor.accept(stop)? {
+            Some(stream) => stream,
+            None => return Ok(()),
+        };
+        let limits = limits::current();
+        let ip = match stream.peer_addr() {
+            Ok(addr) => addr.ip(),
+            Err(_) => continue,
+        };
+        let client = match clients.connect(ip, &limits) {
+            Some(client) if limits.has_room(pool.queued_count()) => client,
+            _ => {
+                if let Err(e) = limits::reject(stream, limits) {
+                    eprintln!("!! {}", e);
+                }
+                continue;
+            }
+        };
         pool.execute(move || {
-            if let Err(e) = server.handle_request(stream) {
+            let _client = client;
+            if let Err(e) = server.handle_request(stream, &options) {
                 eprintln!("!! {}", e);
             }
         });
     }
+}
 
-    Ok(())
+/// Check a request as soon as its headers are in, before its body is
+/// read, so uploads that are going to be refused don't have to be
+/// sent.
+fn check_head(req: &mut Request, limits: &Limits, options: &Options) -> Option<Response> {
+    if req.version() == Version::HTTP11 && req.header("Expect").is_some() && !req.expects_continue()
+    {
+        return Some(Response::from(417));
+    }
+    if !limits.allows_body(req.content_length().unwrap_or(0)) {
+        return Some(Response::from(413));
+    }
+    options.check_head(req)
 }
 
-struct Server {
+pub(crate) struct Server {
     router: Router,
 }
 
 impl Server {
-    pub fn new(router: Router) -> Server {
+    pub(crate) fn new(router: Router) -> Server {
         Server { router }
     }
 
-    fn handle_request(&self, stream: TcpStream) -> Result<()> {
-        let reader = stream.try_clone()?;
-        let req = Request::from_reader(reader)?;
-        self.write_response(stream, req)
+    fn handle_request(&self, stream: Stream, options: &Options) -> Result<()> {
+        let limits = limits::current();
+        let mut reader = Timed::new(stream.try_clone()?, limits.clone());
+        let source = if proxy::uses_proxy_protocol() {
+            proxy::read_proxy_header(&mut reader)?
+        } else {
+            None
+        };
+        let peer = match source {
+            Some(addr) => addr,
+            None => stream.peer_addr()?,
+        };
+
+        let mut refused = None;
+        let read = Request::read(reader, &limits, |req, reader| {
+            proxy::resolve(req, peer);
+            req.set_listener(stream.kind(), options.name());
+            reader.start_body();
+            match check_head(req, &limits, options) {
+                // don't bother reading a body that's too big, or one
+                // the client is holding back until it hears from us
+                Some(res) => {
+                    let read_body = res.code() != 413 && req.header("Expect").is_none();
+                    refused = Some(res);
+                    Ok(read_body)
+                }
+                None => {
+                    if req.expects_continue() {
+                        reader.write_all(b"HTTP/1.1 100 Continue\r\n\r\n")?;
+                        reader.flush()?;
+                    }
+                    Ok(true)
+                }
+            }
+        });
+        match read {
+            Ok(req) => self.write_response(stream, req, refused, options),
+            Err(Error::RequestTimeout) => {
+                println!("- 408 -");
+                Response::from(408).write(Timed::writer(stream, limits)?)
+            }
+            Err(e) => match e.status() {
+                Some(code) => {
+                    println!("- {} - {}", code, e);
+                    Response::from(code).write(Timed::writer(stream, limits)?)
+                }
+                None => Err(e),
+            },
+        }
     }
 
-    fn write_response(&self, stream: TcpStream, req: Request) -> Result<()> {
+    fn write_response(
+        &self,
+        stream: Stream,
+        mut req: Request,
+        refused: Option<Response>,
+        options: &Options,
+    ) -> Result<()> {
         let panic_writer = Arc::new(Mutex::new(stream.try_clone()?));
         std::panic::set_hook(Box::new(move |info| {
             let mut res: Vec<u8> = vec![];
@@ -75,17 +282,78 @@ impl Server {
 
         let method = req.method().to_string();
         let path = req.path().to_string();
-        let response = self.build_response(req);
+        #[cfg(feature = "websocket")]
+        let websocket_key = websocket::upgrade_key(&req);
+        #[allow(unused_mut)]
+        let mut response = match refused.or_else(|| options.filter(&mut req)) {
+            Some(res) => res.with_version(req.version()),
+            None => self.build_response(req),
+        };
+
+        #[cfg(feature = "websocket")]
+        if let Some(handler) = response.take_websocket() {
+            if let Some(key) = websocket_key {
+                println!("{} {} {}", method, response.code(), path);
+                limits::hand_off(&stream)?;
+                return websocket::start(stream, &key, &response, handler);
+            }
+            response = Response::from(400).with_body("400 Bad Request: Expected WebSocket Upgrade");
+        }
 
         println!("{} {} {}", method, response.code(), path);
+        if let Some(handler) = response.take_events() {
+            limits::hand_off(&stream)?;
+            return sse::start(stream, response, handler);
+        }
         if response.code() == 500 {
             eprintln!("{}", response.body());
         }
 
-        response.write(stream)
+        response.write(Timed::writer(stream, limits::current())?)
+    }
+
+    pub(crate) fn build_response(&self, req: Request) -> Response {
+        let version = req.version();
+        let res = if req.full_path() == "*" {
+            self.server_options(&req)
+        } else {
+            self.respond(req)
+        };
+        res.with_version(version)
+    }
+
+    /// Answer a request for the server as a whole, rather than any
+    /// path: `OPTIONS *`, which asks which methods it supports.
+    fn server_options(&self, req: &Request) -> Response {
+        if req.method() == "OPTIONS" {
+            Response::from(200).with_header("Allow", ALLOWED_METHODS)
+        } else {
+            Response::from(400)
+        }
+    }
+
+    /// Run CORS, then find and run the asset or action for a request.
+    fn respond(&self, req: Request) -> Response {
+        let cors = cors::current();
+        let origin = match &cors {
+            Some(cors) => {
+                if let Some(res) = cors.preflight(&req) {
+                    return res;
+                }
+                req.header("Origin").map(|o| o.into_owned())
+            }
+            None => None,
+        };
+
+        let mut res = self.route(req);
+        if let (Some(cors), Some(origin)) = (cors, origin) {
+            cors.decorate(&origin, &mut res);
+        }
+        res
     }
 
-    fn build_response(&self, mut req: Request) -> Response {
+    /// Find and run the asset or action for a request.
+    fn route(&self, mut req: Request) -> Response {
         if asset::exists(req.path()) {
             if let Some(req_etag) = req.header("If-None-Match") {
                 if req_etag == asset::etag(req.path()).as_ref() {
@@ -97,6 +365,21 @@ impl Server {
                 Response::from_asset(req.path())
             }
         } else if let Some(action) = self.router.action_for(&mut req) {
+            #[cfg(feature = "sessions")]
+            {
+                #[cfg(feature = "csrf")]
+                let csrf_token = req.new_csrf_token();
+                let session = req.session_store().clone();
+                #[allow(unused_mut)]
+                let mut res = action(req);
+                #[cfg(feature = "csrf")]
+                if let Some(token) = csrf_token.take() {
+                    res.set_session(crate::csrf::SESSION_KEY, &token);
+                }
+                res.save_session(Some(&session));
+                res
+            }
+            #[cfg(not(feature = "sessions"))]
             action(req)
         } else {
             Response::from(404)
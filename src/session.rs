@@ -1,33 +1,334 @@
+//! Sessions are stored in a single cookie on the client, encrypted
+//! and signed with a secret key so they can't be read or tampered
+//! with. Browsers won't store cookies over 4KB, so keep sessions
+//! small - a session that's too big won't be saved.
+//!
+//! Your app's secret should be set once when it starts, either with
+//! the [`vial::session_secret!()`](../macro.session_secret.html)
+//! macro or the `VIAL_SESSION_SECRET` environment variable:
+//!
+//! ```no_run
+//! vial::routes! {
+//!     GET "/" => |req| req.session("name").unwrap_or("stranger").to_string();
+//! }
+//!
+//! fn main() {
+//!     vial::session_secret!("a long, random, secret string");
+//!     vial::run!().unwrap();
+//! }
+//! ```
+//!
+//! To rotate keys, pass more than one secret. The first is used to
+//! sign new sessions while the rest are still accepted, so existing
+//! sessions keep working until they expire. In `VIAL_SESSION_SECRET`
+//! separate multiple secrets with commas.
+//!
+//! If no secret is set a random one is generated, which means
+//! sessions won't survive a restart.
+//!
+//! Sessions expire after two weeks by default. Use
+//! [`set_max_age()`](fn.set_max_age.html) to change that.
+//!
+//! ## Server-side sessions
+//!
+//! To store more than fits in a cookie, or to be able to kill a
+//! session on the server when someone logs out, give Vial a
+//! [`SessionStore`](trait.SessionStore.html) using
+//! [`set_store()`](fn.set_store.html). The cookie then only carries
+//! a random, signed session ID and the data lives in the store:
+//!
+//! ```no_run
+//! use vial::session::{self, MemoryStore};
+//!
+//! vial::routes! {
+//!     GET "/" => |req| req.session("name").unwrap_or("stranger").to_string();
+//! }
+//!
+//! fn main() {
+//!     vial::session_secret!("a long, random, secret string");
+//!     session::set_store(MemoryStore::new());
+//!     vial::run!().unwrap();
+//! }
+//! ```
+//!
+//! Two stores come with Vial: [`MemoryStore`](struct.MemoryStore.html),
+//! which forgets everything on restart, and
+//! [`FileStore`](struct.FileStore.html), which keeps one file per
+//! session in a directory. Both throw away sessions once they expire.
 use {
-    crate::{Error, Result},
+    crate::{util, Cookie, Error, Result, SameSite},
+    hmac::{Hmac, Mac},
+    sha2::Sha256,
     short_crypt::ShortCrypt,
-    std::collections::HashMap,
+    std::{
+        collections::{HashMap, HashSet},
+        env, fs,
+        path::PathBuf,
+        sync::{Arc, Mutex, RwLock},
+        time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+    },
 };
 
-/// Prefix used on the session cookie.
-pub const PREFIX: &str = "__vial_";
+/// Name of the session cookie.
+pub const COOKIE: &str = "__vial_session";
 
-pub const SECRET: &str = "__temp__";
+/// Browsers only store cookies up to 4KB, including the name.
+pub const MAX_COOKIE_SIZE: usize = 4096;
 
-/// A session store that can encrypt its values.
+/// Environment variable checked for secrets if none are set in code.
+pub const SECRET_ENV: &str = "VIAL_SESSION_SECRET";
+
+/// How long sessions last if `set_max_age()` is never called.
+pub const DEFAULT_MAX_AGE: Duration = Duration::from_secs(60 * 60 * 24 * 14);
+
+/// Global session keys, set once at startup.
+static KEYS: RwLock<Option<Arc<Keys>>> = RwLock::new(None);
+
+/// Global session max age.
+static MAX_AGE: RwLock<Option<Duration>> = RwLock::new(Some(DEFAULT_MAX_AGE));
+
+/// Global server-side session store, if any.
+static STORE: RwLock<Option<Arc<dyn SessionStore>>> = RwLock::new(None);
+
+/// Attributes for the session cookie, if changed from the defaults.
+static COOKIE_ATTRIBUTES: RwLock<Option<Cookie>> = RwLock::new(None);
+
+/// How often stores look for expired sessions to throw away.
+const SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Keys for every secret, newest first.
+#[derive(Debug)]
+struct Keys(Vec<Key>);
+
+/// Keys derived from a single secret.
+#[derive(Debug)]
+struct Key {
+    crypt: String,
+    mac: Vec<u8>,
+}
+
+impl Key {
+    fn new(secret: &str) -> Key {
+        Key {
+            crypt: util::hex(&hmac(secret.as_bytes(), b"vial-session-crypt")),
+            mac: hmac(secret.as_bytes(), b"vial-session-mac"),
+        }
+    }
+}
+
+impl Keys {
+    fn new(secrets: &[&str]) -> Keys {
+        Keys(secrets.iter().map(|s| Key::new(s)).collect())
+    }
+
+    /// Keys from `VIAL_SESSION_SECRET` or a random secret.
+    fn from_env() -> Keys {
+        if let Ok(secrets) = env::var(SECRET_ENV) {
+            let secrets = secrets
+                .split(',')
+                .map(|s| s.trim())
+                .filter(|s| !s.is_empty())
+                .collect::<Vec<_>>();
+            if !secrets.is_empty() {
+                return Keys::new(&secrets);
+            }
+        }
+
+        eprintln!(
+            "! vial: no session secret set, using a random one. Sessions won't survive a restart."
+        );
+        let mut bytes = [0u8; 32];
+        getrandom::getrandom(&mut bytes).expect("Can't generate session secret");
+        Keys::new(&[&util::hex(&bytes)])
+    }
+}
+
+/// The current keys, loading them from the environment if they
+/// haven't been set yet.
+fn keys() -> Arc<Keys> {
+    if let Some(keys) = KEYS.read().unwrap().as_ref() {
+        return keys.clone();
+    }
+    let mut lock = KEYS.write().unwrap();
+    lock.get_or_insert_with(|| Arc::new(Keys::from_env()))
+        .clone()
+}
+
+/// Set the secrets used to encrypt and sign sessions. The first is
+/// used for new sessions, the rest are only used to read existing
+/// ones. Should be called before your app starts.
+///
+/// Panics if `secrets` is empty.
+pub fn set_secrets(secrets: &[&str]) {
+    assert!(!secrets.is_empty(), "Need at least one session secret");
+    *KEYS.write().unwrap() = Some(Arc::new(Keys::new(secrets)));
+}
+
+/// Set the secret used to encrypt and sign sessions.
+pub fn set_secret(secret: &str) {
+    set_secrets(&[secret]);
+}
+
+/// How long a session is valid for after it's written. `None` means
+/// sessions never expire.
+pub fn set_max_age(max_age: Option<Duration>) {
+    *MAX_AGE.write().unwrap() = max_age;
+}
+
+/// Change the attributes of the session cookie, like to add `Secure`
+/// when your app is served over HTTPS. The cookie's value is ignored.
+///
+/// Defaults to `Path=/`, `HttpOnly`, and `SameSite=Lax`.
+pub fn set_cookie_attributes(cookie: Cookie) {
+    *COOKIE_ATTRIBUTES.write().unwrap() = Some(cookie);
+}
+
+/// The session cookie, with its attributes, holding `value`.
+pub(crate) fn cookie(value: &str) -> Cookie {
+    COOKIE_ATTRIBUTES
+        .read()
+        .unwrap()
+        .clone()
+        .unwrap_or_else(|| {
+            Cookie::default()
+                .path("/")
+                .http_only(true)
+                .same_site(SameSite::Lax)
+        })
+        .with_value(value)
+}
+
+/// Keep session data on the server using `store`, with only the
+/// session ID sent to the client. Should be called before your app
+/// starts.
+pub fn set_store<S: SessionStore + 'static>(store: S) {
+    *STORE.write().unwrap() = Some(Arc::new(store));
+}
+
+/// The app's server-side session store, if one was set.
+fn store() -> Option<Arc<dyn SessionStore>> {
+    STORE.read().unwrap().clone()
+}
+
+/// A new, random session ID.
+fn new_id() -> String {
+    let mut bytes = [0u8; 32];
+    getrandom::getrandom(&mut bytes).expect("Can't generate session ID");
+    util::base64_url(&bytes)
+}
+
+/// Session data as a `name=value&name=value` string.
+fn serialize(data: &HashMap<String, String>) -> String {
+    let mut pairs = data.iter().collect::<Vec<_>>();
+    pairs.sort();
+    pairs
+        .iter()
+        .map(|(k, v)| format!("{}={}", util::percent_encode(k), util::percent_encode(v)))
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+/// Session data from a string created by `serialize()`.
+fn deserialize(raw: &str) -> Option<HashMap<String, String>> {
+    let mut data = HashMap::new();
+    for pair in raw.split('&').filter(|p| !p.is_empty()) {
+        let (k, v) = pair.split_once('=').unwrap_or((pair, ""));
+        data.insert(util::percent_decode(k)?, util::percent_decode(v)?);
+    }
+    Some(data)
+}
+
+/// HMAC-SHA256 of `data` using `key`.
+fn hmac(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC takes keys of any size");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+/// Seconds since the Unix epoch.
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// A session store that can encrypt its values. It keeps track of
+/// what's been changed so a `Response` only has to know about the
+/// values it set or removed.
+#[derive(Clone)]
 pub struct Session {
     store: HashMap<String, String>,
-    secret: String,
+    removed: HashSet<String>,
+    cleared: bool,
+    dirty: bool,
+    regenerated: bool,
+    keys: Arc<Keys>,
+    max_age: Option<Duration>,
+
+    /// ID and backend for server-side sessions.
+    id: Option<String>,
+    backend: Option<Arc<dyn SessionStore>>,
+}
+
+impl Default for Session {
+    /// Session using the app's configured secrets, max age, and
+    /// store.
+    fn default() -> Session {
+        let mut session = Session::with_keys(keys(), *MAX_AGE.read().unwrap());
+        session.backend = store();
+        session
+    }
 }
 
 impl Session {
-    /// Create a new session store with a secret key.
+    /// Create a new session store with a secret key, ignoring the
+    /// app's configured secrets. Sessions never expire.
     pub fn new(secret: &str) -> Session {
+        Session::with_secrets(&[secret], None)
+    }
+
+    /// Create a new session store that encodes with the first
+    /// secret, decodes with any of them, and expires values after
+    /// `max_age`.
+    pub fn with_secrets(secrets: &[&str], max_age: Option<Duration>) -> Session {
+        assert!(!secrets.is_empty(), "Need at least one session secret");
+        Session::with_keys(Arc::new(Keys::new(secrets)), max_age)
+    }
+
+    fn with_keys(keys: Arc<Keys>, max_age: Option<Duration>) -> Session {
         Session {
             store: HashMap::new(),
-            secret: secret.to_string(),
+            removed: HashSet::new(),
+            cleared: false,
+            dirty: false,
+            regenerated: false,
+            keys,
+            max_age,
+            id: None,
+            backend: None,
         }
     }
 
+    /// Keep this session's data in `store` instead of a cookie,
+    /// ignoring the app's configured store.
+    pub fn with_store(mut self, store: Arc<dyn SessionStore>) -> Session {
+        self.backend = Some(store);
+        self
+    }
+
+    /// ID of a server-side session. `None` for cookie sessions, and
+    /// for server-side sessions that haven't been saved yet.
+    pub fn id(&self) -> Option<&str> {
+        self.id.as_deref()
+    }
+
     /// Set a session value to the store.
     pub fn set(&mut self, name: &str, val: &str) {
-        self.store
-            .insert(name.to_lowercase().to_owned(), val.to_owned());
+        let name = name.to_lowercase();
+        self.removed.remove(&name);
+        self.store.insert(name, val.to_owned());
+        self.dirty = true;
     }
 
     /// Get a session value from the store.
@@ -37,21 +338,368 @@ impl Session {
 
     /// Remove a session value from the store.
     pub fn remove(&mut self, name: &str) {
-        self.store
-            .insert(name.to_lowercase().to_owned(), "".to_owned());
+        let name = name.to_lowercase();
+        self.store.remove(&name);
+        self.removed.insert(name);
+        self.dirty = true;
+    }
+
+    /// Remove every value from the session, like when logging out.
+    pub fn clear(&mut self) {
+        self.store.clear();
+        self.removed.clear();
+        self.cleared = true;
+        self.dirty = true;
+    }
+
+    /// Write the session out again even if nothing has changed,
+    /// giving it a fresh expiration time. Server-side sessions also
+    /// get a new ID, and the old one is thrown away. Call this after
+    /// logging someone in or changing their privileges.
+    pub fn regenerate(&mut self) {
+        self.regenerated = true;
+        self.dirty = true;
     }
 
-    /// Encode a value using the session secret.
+    /// Has the session been changed since it was loaded?
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
+    /// Forget about any changes, once they've been written out.
+    pub(crate) fn mark_saved(&mut self) {
+        self.removed.clear();
+        self.cleared = false;
+        self.regenerated = false;
+        self.dirty = false;
+    }
+
+    /// Does the session have no values?
+    pub fn is_empty(&self) -> bool {
+        self.store.is_empty()
+    }
+
+    /// Iterator over all `(name, value)` pairs in the session.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.store.iter().map(|(k, v)| (k.as_ref(), v.as_ref()))
+    }
+
+    /// Apply the changes made to this session on top of `base`,
+    /// which is usually the session the client sent. Values that
+    /// were set or removed here win, and nothing from `base` is kept
+    /// if this session was cleared.
+    pub fn merge(&mut self, base: &Session) {
+        if self.id.is_none() {
+            self.id = base.id.clone();
+        }
+        if self.cleared {
+            return;
+        }
+        for (k, v) in &base.store {
+            if !self.removed.contains(k) && !self.store.contains_key(k) {
+                self.store.insert(k.clone(), v.clone());
+            }
+        }
+    }
+
+    /// Serialize, encrypt, and sign the whole session into a single
+    /// cookie value. Fails if the cookie would be too big for a
+    /// browser to store.
+    pub fn to_cookie(&self) -> Result<String> {
+        let cookie = self.encode(&serialize(&self.store));
+        let size = COOKIE.len() + 1 + cookie.len();
+        if size > MAX_COOKIE_SIZE {
+            Err(Error::SessionTooLarge(size))
+        } else {
+            Ok(cookie)
+        }
+    }
+
+    /// Load the session from a cookie value created by
+    /// [`to_cookie()`](#method.to_cookie) or
+    /// [`save()`](#method.save), replacing any values in the store.
+    /// On error the session is left empty.
+    pub fn load_cookie(&mut self, cookie: &str) -> Result<()> {
+        self.store.clear();
+        self.id = None;
+
+        let raw = self.decode(cookie)?;
+        if let Some(backend) = &self.backend {
+            self.store = backend.load(&raw).ok_or(Error::SessionDecode)?;
+            self.id = Some(raw);
+        } else {
+            self.store = deserialize(&raw).ok_or(Error::SessionDecode)?;
+        }
+        Ok(())
+    }
+
+    /// Save the session, returning the value to send in the session
+    /// cookie or `None` if the cookie should be removed.
+    ///
+    /// For server-side sessions this writes the data to the store
+    /// and returns the signed session ID. Empty and cleared sessions
+    /// are removed from the store.
+    pub fn save(&mut self) -> Result<Option<String>> {
+        let backend = match &self.backend {
+            Some(backend) => backend.clone(),
+            None if self.store.is_empty() => return Ok(None),
+            None => return self.to_cookie().map(Some),
+        };
+
+        if self.cleared || self.regenerated || self.store.is_empty() {
+            if let Some(old) = self.id.take() {
+                backend.remove(&old);
+            }
+        }
+        if self.store.is_empty() {
+            return Ok(None);
+        }
+
+        let id = self.id.get_or_insert_with(new_id).clone();
+        backend.save(&id, &self.store, self.max_age)?;
+        Ok(Some(self.encode(&id)))
+    }
+
+    /// Encrypt and sign a value using the newest session secret.
+    /// The value's expiration time is stored along with it.
     pub fn encode(&self, val: &str) -> String {
-        let sc = ShortCrypt::new(&self.secret);
-        sc.encrypt_to_url_component(val)
+        let expires = self.max_age.map(|age| now() + age.as_secs()).unwrap_or(0);
+        let key = &self.keys.0[0];
+        let sc = ShortCrypt::new(&key.crypt);
+        let data = sc.encrypt_to_url_component(&format!("{}:{}", expires, val));
+        let tag = util::base64_url(&hmac(&key.mac, data.as_bytes()));
+        format!("{}.{}", data, tag)
     }
 
-    /// Decode a value using the session secret.
+    /// Verify and decrypt a value using any of the session secrets.
+    /// Fails if the value has been tampered with or has expired.
     pub fn decode(&self, val: &str) -> Result<String> {
-        let sc = ShortCrypt::new(&self.secret);
-        sc.decrypt_url_component(val)
-            .map(|v| String::from_utf8_lossy(&v).to_string())
-            .map_err(|_| Error::SessionDecode)
+        let (data, tag) = val.rsplit_once('.').ok_or(Error::SessionDecode)?;
+        let key = self
+            .keys
+            .0
+            .iter()
+            .find(|key| {
+                let expected = util::base64_url(&hmac(&key.mac, data.as_bytes()));
+                util::constant_time_eq(expected.as_bytes(), tag.as_bytes())
+            })
+            .ok_or(Error::SessionDecode)?;
+
+        let sc = ShortCrypt::new(&key.crypt);
+        let plain = sc
+            .decrypt_url_component(data)
+            .map_err(|_| Error::SessionDecode)?;
+        let plain = String::from_utf8(plain).map_err(|_| Error::SessionDecode)?;
+        let (expires, val) = plain.split_once(':').ok_or(Error::SessionDecode)?;
+        let expires: u64 = expires.parse().map_err(|_| Error::SessionDecode)?;
+        if expires != 0 && expires < now() {
+            return Err(Error::SessionExpired);
+        }
+
+        Ok(val.to_string())
+    }
+}
+
+/// Where server-side sessions are kept. Implement this to store
+/// sessions in your database, or use one of the built in stores.
+///
+/// Session IDs are random, URL-safe strings created by Vial.
+pub trait SessionStore: Send + Sync {
+    /// Get the data for a session, or `None` if it doesn't exist or
+    /// has expired.
+    fn load(&self, id: &str) -> Option<HashMap<String, String>>;
+
+    /// Create or replace the data for a session, which should be
+    /// thrown away after `max_age`. `None` means it never expires.
+    fn save(
+        &self,
+        id: &str,
+        data: &HashMap<String, String>,
+        max_age: Option<Duration>,
+    ) -> Result<()>;
+
+    /// Throw away a session, like when someone logs out.
+    fn remove(&self, id: &str);
+}
+
+/// Keeps sessions in memory. They're lost when your app restarts.
+#[derive(Debug)]
+pub struct MemoryStore {
+    sessions: Mutex<MemorySessions>,
+}
+
+#[derive(Debug)]
+struct MemorySessions {
+    map: HashMap<String, (HashMap<String, String>, Option<Instant>)>,
+    last_sweep: Instant,
+}
+
+impl Default for MemoryStore {
+    fn default() -> MemoryStore {
+        MemoryStore {
+            sessions: Mutex::new(MemorySessions {
+                map: HashMap::new(),
+                last_sweep: Instant::now(),
+            }),
+        }
+    }
+}
+
+impl MemoryStore {
+    /// Create a new, empty store.
+    pub fn new() -> MemoryStore {
+        MemoryStore::default()
+    }
+
+    /// Number of sessions in the store, including expired ones that
+    /// haven't been thrown away yet.
+    pub fn len(&self) -> usize {
+        self.sessions.lock().unwrap().map.len()
+    }
+
+    /// Is the store empty?
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Throw away all expired sessions. Happens automatically every
+    /// so often when sessions are saved.
+    pub fn sweep(&self) {
+        let mut sessions = self.sessions.lock().unwrap();
+        let now = Instant::now();
+        sessions
+            .map
+            .retain(|_, (_, expires)| expires.map(|e| e > now).unwrap_or(true));
+        sessions.last_sweep = now;
+    }
+}
+
+impl SessionStore for MemoryStore {
+    fn load(&self, id: &str) -> Option<HashMap<String, String>> {
+        let sessions = self.sessions.lock().unwrap();
+        let (data, expires) = sessions.map.get(id)?;
+        if expires.map(|e| e <= Instant::now()).unwrap_or(false) {
+            None
+        } else {
+            Some(data.clone())
+        }
+    }
+
+    fn save(
+        &self,
+        id: &str,
+        data: &HashMap<String, String>,
+        max_age: Option<Duration>,
+    ) -> Result<()> {
+        if self.sessions.lock().unwrap().last_sweep.elapsed() > SWEEP_INTERVAL {
+            self.sweep();
+        }
+        let expires = max_age.map(|age| Instant::now() + age);
+        self.sessions
+            .lock()
+            .unwrap()
+            .map
+            .insert(id.to_string(), (data.clone(), expires));
+        Ok(())
+    }
+
+    fn remove(&self, id: &str) {
+        self.sessions.lock().unwrap().map.remove(id);
+    }
+}
+
+/// Keeps each session in its own file in a directory, so they
+/// survive restarts.
+#[derive(Debug)]
+pub struct FileStore {
+    dir: PathBuf,
+    last_sweep: Mutex<Instant>,
+}
+
+impl FileStore {
+    /// Store sessions in `dir`, creating it if needed.
+    pub fn new<P: Into<PathBuf>>(dir: P) -> Result<FileStore> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+        Ok(FileStore {
+            dir,
+            last_sweep: Mutex::new(Instant::now()),
+        })
+    }
+
+    /// Path to the file for a session. `None` if the ID isn't one
+    /// Vial would create, so it can't be used to escape `dir`.
+    fn path(&self, id: &str) -> Option<PathBuf> {
+        if id.is_empty()
+            || !id
+                .bytes()
+                .all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_')
+        {
+            return None;
+        }
+        Some(self.dir.join(id))
+    }
+
+    /// Read a session file, returning its expiration time (`0` for
+    /// never) and data.
+    fn read(path: &PathBuf) -> Option<(u64, HashMap<String, String>)> {
+        let contents = fs::read_to_string(path).ok()?;
+        let (expires, raw) = contents.split_once('\n')?;
+        Some((expires.parse().ok()?, deserialize(raw)?))
+    }
+
+    /// Delete all expired session files. Happens automatically every
+    /// so often when sessions are saved. Files that aren't sessions
+    /// are left alone, in case `dir` is shared with something else.
+    pub fn sweep(&self) -> Result<()> {
+        *self.last_sweep.lock().unwrap() = Instant::now();
+        let now = now();
+        for entry in fs::read_dir(&self.dir)? {
+            let entry = entry?;
+            let path = match entry.file_name().to_str().and_then(|id| self.path(id)) {
+                Some(path) if entry.file_type()?.is_file() => path,
+                _ => continue,
+            };
+            if let Some((expires, _)) = FileStore::read(&path) {
+                if expires != 0 && expires <= now {
+                    let _ = fs::remove_file(path);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl SessionStore for FileStore {
+    fn load(&self, id: &str) -> Option<HashMap<String, String>> {
+        let path = self.path(id)?;
+        let (expires, data) = FileStore::read(&path)?;
+        if expires != 0 && expires <= now() {
+            let _ = fs::remove_file(path);
+            None
+        } else {
+            Some(data)
+        }
+    }
+
+    fn save(
+        &self,
+        id: &str,
+        data: &HashMap<String, String>,
+        max_age: Option<Duration>,
+    ) -> Result<()> {
+        if self.last_sweep.lock().unwrap().elapsed() > SWEEP_INTERVAL {
+            self.sweep()?;
+        }
+        let path = self.path(id).ok_or(Error::SessionDecode)?;
+        let expires = max_age.map(|age| now() + age.as_secs()).unwrap_or(0);
+        fs::write(path, format!("{}\n{}", expires, serialize(data)))?;
+        Ok(())
+    }
+
+    fn remove(&self, id: &str) {
+        if let Some(path) = self.path(id) {
+            let _ = fs::remove_file(path);
+        }
     }
 }
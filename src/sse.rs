@@ -0,0 +1,249 @@
+//! Server-Sent Events let your app push a stream of messages to the
+//! browser over a single, long-lived response.
+//!
+//! Return [`Response::sse()`](../struct.Response.html#method.sse)
+//! from an action with a closure that takes an
+//! [`EventSender`](struct.EventSender.html). The closure runs on its
+//! own thread, and every event is flushed to the client as soon as
+//! it's sent:
+//!
+//! ```no_run
+//! use {std::{thread, time::Duration}, vial::{prelude::*, sse::Event}};
+//!
+//! routes! {
+//!     GET "/clock" => |_| Response::sse(|mut events| {
+//!         let mut tick = 0;
+//!         while events.is_connected() {
+//!             tick += 1;
+//!             let event = Event::new(tick.to_string()).id(tick.to_string()).event("tick");
+//!             if events.send(event).is_err() {
+//!                 break;
+//!             }
+//!             thread::sleep(Duration::from_secs(1));
+//!         }
+//!     });
+//! }
+//!
+//! fn main() {
+//!     run!().unwrap();
+//! }
+//! ```
+//!
+//! In the browser, listen with `new EventSource("/clock")`. If the
+//! connection drops the browser reconnects on its own and sends the
+//! last event's `id` in the `Last-Event-ID` header.
+//!
+//! A comment is sent every
+//! [`HEARTBEAT_INTERVAL`](constant.HEARTBEAT_INTERVAL.html) to keep
+//! proxies from closing the connection and to notice when the client
+//! has gone away, at which point
+//! [`is_connected()`](struct.EventSender.html#method.is_connected)
+//! returns `false` and `send()` fails.
+use {
+    crate::{listener::Stream, Error, Response, Result},
+    std::{
+        io::{self, Write},
+        net::Shutdown,
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            mpsc, Arc, Mutex,
+        },
+        thread,
+        time::Duration,
+    },
+};
+
+/// How often a heartbeat comment is sent by default.
+pub const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Function that produces the events for a `Response::sse()`.
+pub(crate) type Handler = Box<dyn FnOnce(EventSender) + Send>;
+
+/// Write the head of `res` to `stream` and run its event handler on a
+/// new thread.
+pub(crate) fn start(mut stream: Stream, res: Response, handler: Handler) -> Result<()> {
+    res.write_head(&mut stream)?;
+    let events = EventSender::new(Closing(stream), Some(HEARTBEAT_INTERVAL));
+    thread::spawn(move || handler(events));
+    Ok(())
+}
+
+/// Shuts the connection down once the last writer is dropped, which
+/// tells the client the stream is over.
+struct Closing(Stream);
+
+impl Write for Closing {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.flush()
+    }
+}
+
+impl Drop for Closing {
+    fn drop(&mut self) {
+        let _ = self.0.shutdown(Shutdown::Both);
+    }
+}
+
+/// A single event. Only `data` is required.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Event {
+    data: String,
+    id: Option<String>,
+    event: Option<String>,
+    retry: Option<Duration>,
+}
+
+/// Strip newlines, which would end a field early.
+fn field(s: &str) -> String {
+    s.chars().filter(|c| *c != '\r' && *c != '\n').collect()
+}
+
+impl Event {
+    /// Create an event with the given data. Data with more than one
+    /// line is sent as several `data:` fields and put back together
+    /// by the browser.
+    pub fn new<S: AsRef<str>>(data: S) -> Event {
+        Event {
+            data: data.as_ref().to_string(),
+            ..Event::default()
+        }
+    }
+
+    /// Set the event's ID, which the browser sends back in the
+    /// `Last-Event-ID` header when it reconnects.
+    pub fn id<S: AsRef<str>>(mut self, id: S) -> Event {
+        self.id = Some(field(id.as_ref()));
+        self
+    }
+
+    /// Set the event's type. Browsers deliver typed events to
+    /// `addEventListener(type, ...)` instead of `onmessage`.
+    pub fn event<S: AsRef<str>>(mut self, event: S) -> Event {
+        self.event = Some(field(event.as_ref()));
+        self
+    }
+
+    /// Tell the browser how long to wait before reconnecting.
+    pub fn retry(mut self, retry: Duration) -> Event {
+        self.retry = Some(retry);
+        self
+    }
+
+    /// The event in `text/event-stream` format, including the blank
+    /// line that ends it.
+    pub fn to_frame(&self) -> String {
+        let mut out = String::new();
+        if let Some(id) = &self.id {
+            out.push_str(&format!("id: {}\n", id));
+        }
+        if let Some(event) = &self.event {
+            out.push_str(&format!("event: {}\n", event));
+        }
+        if let Some(retry) = self.retry {
+            out.push_str(&format!("retry: {}\n", retry.as_millis()));
+        }
+        // browsers end a line at \r\n, \r, or \n, so a bare \r would
+        // start a field of its own
+        for line in self.data.replace("\r\n", "\n").split(['\r', '\n']) {
+            out.push_str(&format!("data: {}\n", line));
+        }
+        out.push('\n');
+        out
+    }
+}
+
+impl From<&str> for Event {
+    fn from(data: &str) -> Event {
+        Event::new(data)
+    }
+}
+
+impl From<String> for Event {
+    fn from(data: String) -> Event {
+        Event {
+            data,
+            ..Event::default()
+        }
+    }
+}
+
+/// Connection shared by an `EventSender` and its heartbeat thread.
+struct Shared {
+    writer: Mutex<Box<dyn Write + Send>>,
+    connected: AtomicBool,
+}
+
+impl Shared {
+    /// Write and flush `data`, noting if the client has gone away.
+    fn write(&self, data: &[u8]) -> Result<()> {
+        if !self.connected.load(Ordering::SeqCst) {
+            return Err(Error::ConnectionClosed);
+        }
+        let mut writer = self.writer.lock().unwrap();
+        if writer.write_all(data).and_then(|_| writer.flush()).is_err() {
+            self.connected.store(false, Ordering::SeqCst);
+            return Err(Error::ConnectionClosed);
+        }
+        Ok(())
+    }
+}
+
+/// Sends events to the client. Dropping it ends the response.
+pub struct EventSender {
+    shared: Arc<Shared>,
+    /// Dropped along with the sender to stop the heartbeat thread.
+    _stop: Option<mpsc::Sender<()>>,
+}
+
+impl EventSender {
+    /// Send events to `writer`, with a heartbeat comment every
+    /// `heartbeat` if it's set. The response head should already
+    /// have been written.
+    pub fn new<W: Write + Send + 'static>(writer: W, heartbeat: Option<Duration>) -> EventSender {
+        let shared = Arc::new(Shared {
+            writer: Mutex::new(Box::new(writer)),
+            connected: AtomicBool::new(true),
+        });
+
+        let stop = heartbeat.map(|interval| {
+            let (stop, stopped) = mpsc::channel::<()>();
+            let shared = shared.clone();
+            thread::spawn(move || {
+                while let Err(mpsc::RecvTimeoutError::Timeout) = stopped.recv_timeout(interval) {
+                    if shared.write(b":\n\n").is_err() {
+                        break;
+                    }
+                }
+            });
+            stop
+        });
+
+        EventSender {
+            shared,
+            _stop: stop,
+        }
+    }
+
+    /// Send an event, or just a string of data, and flush it to the
+    /// client. Fails with `Error::ConnectionClosed` once the client
+    /// has gone away.
+    pub fn send<E: Into<Event>>(&mut self, event: E) -> Result<()> {
+        self.shared.write(event.into().to_frame().as_bytes())
+    }
+
+    /// Send a comment, which browsers ignore.
+    pub fn comment(&mut self, comment: &str) -> Result<()> {
+        self.shared
+            .write(format!(": {}\n\n", field(comment)).as_bytes())
+    }
+
+    /// Is the client still listening, as far as we know? Turns
+    /// `false` when a send or heartbeat fails.
+    pub fn is_connected(&self) -> bool {
+        self.shared.connected.load(Ordering::SeqCst)
+    }
+}
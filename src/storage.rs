@@ -9,11 +9,18 @@ pub fn init() {
 }
 
 pub fn get<T: Send + Sync + 'static>() -> &'static T {
-    unsafe { STORAGE.as_ref().unwrap().get::<T>().as_ref().unwrap() }
+    unsafe {
+        (*std::ptr::addr_of!(STORAGE))
+            .as_ref()
+            .unwrap()
+            .get::<T>()
+            .as_ref()
+            .unwrap()
+    }
 }
 
 pub fn set<T: Send + Sync + 'static>(o: T) {
     unsafe {
-        STORAGE.as_ref().unwrap().set(o);
+        (*std::ptr::addr_of!(STORAGE)).as_ref().unwrap().set(o);
     }
 }
@@ -0,0 +1,265 @@
+//! Vial's test client lets you make requests to your routes without
+//! starting a server or binding a port.
+//!
+//! Requests built with [`Client`](struct.Client.html) are turned into
+//! raw HTTP and parsed the same way the server parses them, then
+//! handed to your routes, filters, and assets just like a real
+//! request would be:
+//!
+//! ```rust
+//! use vial::{test::Client, Router};
+//!
+//! mod app {
+//!     vial::routes! {
+//!         GET "/" => |_| "Hello, world!";
+//!         POST "/echo" => |req| req.form("echo").unwrap_or("?").to_string();
+//!     }
+//! }
+//!
+//! let mut router = Router::new();
+//! app::vial_add_to_router(&mut router);
+//! let client = Client::new(router);
+//!
+//! let res = client.get("/").send();
+//! assert_eq!(200, res.code());
+//! assert_eq!("Hello, world!", res.body());
+//!
+//! let res = client.post("/echo").form("echo", "Hi there!").send();
+//! assert_eq!("Hi there!", res.body());
+//! ```
+//!
+//! Server-Sent Events are run to completion on the calling thread and
+//! end up in the response's body, so make sure your `Response::sse()`
+//! handler returns.
+//!
+//! If the `cookies` feature is enabled, the `Client` remembers any
+//! cookies (and sessions) set by a response and sends them along
+//! with the next request, like a browser would.
+use {
+    crate::{
+        limits, proxy, server::Server, sse::EventSender, Method, Request, RequestBuilder, Response,
+        Router,
+    },
+    std::{
+        io,
+        net::SocketAddr,
+        panic,
+        sync::{Arc, Mutex},
+    },
+};
+
+#[cfg(feature = "cookies")]
+use std::{cell::RefCell, collections::HashMap};
+
+/// Makes requests to a [`Router`](../struct.Router.html) without
+/// going over the network. Use one of the HTTP method functions,
+/// like [`get()`](#method.get), to start building a request.
+pub struct Client {
+    server: Server,
+
+    #[cfg(feature = "cookies")]
+    cookies: RefCell<HashMap<String, String>>,
+}
+
+impl Client {
+    /// Create a new test client for the given router.
+    pub fn new(router: Router) -> Client {
+        Client {
+            server: Server::new(router),
+
+            #[cfg(feature = "cookies")]
+            cookies: RefCell::new(HashMap::new()),
+        }
+    }
+
+    /// Start building a request with an arbitrary HTTP method.
+    pub fn request<T: Into<Method>>(&self, method: T, path: &str) -> TestRequest<'_> {
+        TestRequest {
+            client: self,
+            builder: Request::builder()
+                .method(&format!("{:?}", method.into()))
+                .path(path),
+            remote_addr: None,
+        }
+    }
+
+    /// Start building a `GET` request.
+    pub fn get(&self, path: &str) -> TestRequest<'_> {
+        self.request(Method::GET, path)
+    }
+
+    /// Start building a `HEAD` request.
+    pub fn head(&self, path: &str) -> TestRequest<'_> {
+        self.request(Method::HEAD, path)
+    }
+
+    /// Start building a `POST` request.
+    pub fn post(&self, path: &str) -> TestRequest<'_> {
+        self.request(Method::POST, path)
+    }
+
+    /// Start building a `PUT` request.
+    pub fn put(&self, path: &str) -> TestRequest<'_> {
+        self.request(Method::PUT, path)
+    }
+
+    /// Start building a `PATCH` request.
+    pub fn patch(&self, path: &str) -> TestRequest<'_> {
+        self.request(Method::PATCH, path)
+    }
+
+    /// Start building a `DELETE` request.
+    pub fn delete(&self, path: &str) -> TestRequest<'_> {
+        self.request(Method::DELETE, path)
+    }
+
+    /// Start building an `OPTIONS` request.
+    pub fn options(&self, path: &str) -> TestRequest<'_> {
+        self.request(Method::OPTIONS, path)
+    }
+
+    #[cfg(feature = "cookies")]
+    /// Value of a cookie the client is holding on to, if any. `name`
+    /// is case sensitive, like it is for browsers.
+    pub fn cookie(&self, name: &str) -> Option<String> {
+        self.cookies.borrow().get(name).cloned()
+    }
+
+    #[cfg(feature = "cookies")]
+    /// Forget all cookies, like closing the browser.
+    pub fn clear_cookies(&self) {
+        self.cookies.borrow_mut().clear();
+    }
+
+    /// Parse the raw request and run it through the router.
+    fn dispatch(&self, raw: Vec<u8>, peer: Option<SocketAddr>) -> Response {
+        let mut req = match Request::read(io::Cursor::new(raw), &limits::current(), |_, _| Ok(true))
+        {
+            Ok(req) => req,
+            Err(e) => return Response::from(400).with_body(e.to_string()),
+        };
+        let peer = peer.unwrap_or(*req.remote_addr());
+        proxy::resolve(&mut req, peer);
+
+        let mut res = match panic::catch_unwind(panic::AssertUnwindSafe(|| {
+            self.server.build_response(req)
+        })) {
+            Ok(res) => res,
+            Err(_) => Response::from(500),
+        };
+
+        if let Some(handler) = res.take_events() {
+            let events = Captured::default();
+            handler(EventSender::new(events.clone(), None));
+            let body = String::from_utf8_lossy(&events.0.lock().unwrap()).into_owned();
+            res.set_body(body);
+        }
+
+        if let Err(e) = res.buffer_body() {
+            return Response::from_error(e);
+        }
+
+        #[cfg(feature = "cookies")]
+        {
+            let mut jar = self.cookies.borrow_mut();
+            for (name, cookie) in res.cookies() {
+                if cookie.is_removal() {
+                    jar.remove(name);
+                } else {
+                    jar.insert(name.clone(), cookie.value().to_string());
+                }
+            }
+        }
+
+        res
+    }
+}
+
+/// A request being built by a [`Client`](struct.Client.html). Call
+/// [`send()`](#method.send) to deliver it and get back a `Response`.
+pub struct TestRequest<'c> {
+    client: &'c Client,
+    builder: RequestBuilder,
+    remote_addr: Option<SocketAddr>,
+}
+
+impl TestRequest<'_> {
+    /// Add a header to the request.
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.builder = self.builder.header(name, value);
+        self
+    }
+
+    /// Add a ?query value.
+    pub fn query(mut self, name: &str, value: &str) -> Self {
+        self.builder = self.builder.query(name, value);
+        self
+    }
+
+    /// Add a form field. The body will be sent as
+    /// `application/x-www-form-urlencoded`.
+    pub fn form(mut self, name: &str, value: &str) -> Self {
+        self.builder = self.builder.form(name, value);
+        self
+    }
+
+    /// Make the request come from `addr` instead of `127.0.0.1`.
+    pub fn remote_addr(mut self, addr: SocketAddr) -> Self {
+        self.remote_addr = Some(addr);
+        self
+    }
+
+    /// Set the raw body of the request.
+    pub fn body<B: AsRef<[u8]>>(mut self, body: B) -> Self {
+        self.builder = self.builder.body(body);
+        self
+    }
+
+    /// Set the body to a JSON string and the `Content-Type` to
+    /// `application/json`.
+    pub fn json<S: AsRef<str>>(self, json: S) -> Self {
+        self.header("Content-Type", "application/json")
+            .body(json.as_ref())
+    }
+
+    #[cfg(feature = "cookies")]
+    /// Send a cookie along with the request, in addition to the ones
+    /// the client is already holding on to.
+    pub fn cookie(mut self, name: &str, value: &str) -> Self {
+        self.builder = self.builder.cookie(name, value);
+        self
+    }
+
+    /// Deliver the request and return the `Response`. Bodies backed
+    /// by an `io::Read`, like files and assets, are read into memory
+    /// so they can be checked with
+    /// [`Response::body()`](../struct.Response.html#method.body).
+    pub fn send(self) -> Response {
+        #[allow(unused_mut)]
+        let mut builder = self.builder;
+
+        #[cfg(feature = "cookies")]
+        {
+            for (name, val) in self.client.cookies.borrow().iter() {
+                builder = builder.cookie(name, val);
+            }
+        }
+
+        self.client.dispatch(builder.to_bytes(), self.remote_addr)
+    }
+}
+
+/// Collects the events sent by a `Response::sse()` handler.
+#[derive(Clone, Default)]
+struct Captured(Arc<Mutex<Vec<u8>>>);
+
+impl io::Write for Captured {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
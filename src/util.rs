@@ -19,105 +19,219 @@ pub fn http_current_date() -> String {
     libc_strftime::strftime_gmt(HTTP_DATE_FMT, now) + " GMT"
 }
 
+/// Reason phrase for an HTTP status code, like `"Not Found"` for
+/// `404`. Empty for codes we don't know, which HTTP allows.
+pub fn reason_phrase(code: usize) -> &'static str {
+    match code {
+        100 => "Continue",
+        101 => "Switching Protocols",
+        200 => "OK",
+        201 => "Created",
+        202 => "Accepted",
+        203 => "Non-Authoritative Information",
+        204 => "No Content",
+        205 => "Reset Content",
+        206 => "Partial Content",
+        300 => "Multiple Choices",
+        301 => "Moved Permanently",
+        302 => "Found",
+        303 => "See Other",
+        304 => "Not Modified",
+        307 => "Temporary Redirect",
+        308 => "Permanent Redirect",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        402 => "Payment Required",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        406 => "Not Acceptable",
+        407 => "Proxy Authentication Required",
+        408 => "Request Timeout",
+        409 => "Conflict",
+        410 => "Gone",
+        411 => "Length Required",
+        412 => "Precondition Failed",
+        413 => "Content Too Large",
+        414 => "URI Too Long",
+        415 => "Unsupported Media Type",
+        416 => "Range Not Satisfiable",
+        417 => "Expectation Failed",
+        418 => "I'm a teapot",
+        421 => "Misdirected Request",
+        422 => "Unprocessable Content",
+        425 => "Too Early",
+        426 => "Upgrade Required",
+        428 => "Precondition Required",
+        429 => "Too Many Requests",
+        431 => "Request Header Fields Too Large",
+        451 => "Unavailable For Legal Reasons",
+        500 => "Internal Server Error",
+        501 => "Not Implemented",
+        502 => "Bad Gateway",
+        503 => "Service Unavailable",
+        504 => "Gateway Timeout",
+        505 => "HTTP Version Not Supported",
+        _ => "",
+    }
+}
+
 /// Mutably borrowed from the zero dependency httpserv project.
 /// https://github.com/nic-hartley/httpserv/blob/585c020/src/http.rs
-pub fn percent_decode(mut inp: &str) -> Option<String> {
-    let mut out = Vec::new();
-    loop {
-        let next_pct = match inp.find('%') {
-            Some(l) if inp.len() > 1 && l < inp.len() - 2 => l,
-            Some(_) => return None,
-            None => break,
-        };
-        let (push, pct_rest) = inp.split_at(next_pct);
-        out.extend_from_slice(push.as_bytes());
-        let (pct, rest) = pct_rest.split_at(3);
-        inp = rest;
-        let val = u8::from_str_radix(&pct[1..], 16).ok()?;
-        out.push(val);
+pub fn percent_decode(inp: &str) -> Option<String> {
+    let inp = inp.as_bytes();
+    let mut out = Vec::with_capacity(inp.len());
+    let mut i = 0;
+    while i < inp.len() {
+        if inp[i] == b'%' {
+            let hi = (*inp.get(i + 1)? as char).to_digit(16)?;
+            let lo = (*inp.get(i + 2)? as char).to_digit(16)?;
+            out.push((hi * 16 + lo) as u8);
+            i += 3;
+        } else {
+            out.push(inp[i]);
+            i += 1;
+        }
     }
-    out.extend_from_slice(inp.as_bytes());
     String::from_utf8(out).ok()
 }
 
-/// Content type for a file based on its extension.
-/// https://developer.mozilla.org/en-US/docs/Web/HTTP/Basics_of_HTTP/MIME_types/Common_types
-pub fn content_type(path: &str) -> &'static str {
-    match path
-        .split('.')
-        .last()
-        .unwrap_or("?")
-        .to_lowercase()
-        .as_ref()
-    {
-        "aac" => "audio/aac",
-        "abw" => "application/x-abiword",
-        "arc" => "application/x-freearc",
-        "avi" => "video/x-msvideo",
-        "azw" => "application/vnd.amazon.ebook",
-        "bin" => "application/octet-stream",
-        "bmp" => "image/bmp",
-        "bz" => "application/x-bzip",
-        "bz2" => "application/x-bzip2",
-        "csh" => "application/x-csh",
-        "css" => "text/css; charset=utf8",
-        "csv" => "text/csv",
-        "doc" => "application/msword",
-        "docx" => "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
-        "eot" => "application/vnd.ms-fontobject",
-        "epub" => "application/epub+zip",
-        "gz" => "gzip",
-        "gif" => "image/gif",
-        "htm" | "html" => "text/html; charset=utf8",
-        "ico" => "image/vnd.microsoft.icon",
-        "ics" => "text/calendar",
-        "jar" => "application/java-archive",
-        "jpeg" | "jpg" => "image/jpeg",
-        "js" => "text/javascript",
-        "json" => "application/json",
-        "jsonld" => "application/ld+json",
-        "mid" | "midi" => "audio/midi",
-        "mjs" => "text/javascript",
-        "mp3" => "audio/mpeg",
-        "mpeg" => "video/mpeg",
-        "mpkg" => "application/vnd.apple.installer+xml",
-        "odp" => "application/vnd.oasis.opendocument.presentation",
-        "ods" => "application/vnd.oasis.opendocument.spreadsheet",
-        "odt" => "application/vnd.oasis.opendocument.text",
-        "oga" => "audio/ogg",
-        "ogv" => "video/ogg",
-        "ogx" => "application/ogg",
-        "opus" => "audio/opus",
-        "otf" => "font/otf",
-        "png" => "image/png",
-        "pdf" => "application/pdf",
-        "php" => "application/x-httpd-php",
-        "ppt" => "application/vnd.ms-powerpoint",
-        "pptx" => "application/vnd.openxmlformats-officedocument.presentationml.presentation",
-        "rar" => "application/vnd.rar",
-        "rtf" => "application/rtf",
-        "sh" => "application/x-sh",
-        "svg" => "image/svg+xml",
-        "swf" => "application/x-shockwave-flash",
-        "tar" => "application/x-tar",
-        "tif" | "tiff" => "image/tiff",
-        "ts" => "video/mp2t",
-        "ttf" => "font/ttf",
-        "txt" => "text/plain; charset=utf8",
-        "vsd" => "application/vnd.visio",
-        "wav" => "audio/wav",
-        "weba" => "audio/webm",
-        "webm" => "video/webm",
-        "webp" => "image/webp",
-        "woff" => "font/woff",
-        "woff2" => "font/woff2",
-        "xhtml" => "application/xhtml+xml",
-        "xls" => "application/vnd.ms-excel",
-        "xlsx" => "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
-        "xul" => "application/vnd.mozilla.xul+xml",
-        "xml" => "application/xml",
-        "zip" => "application/zip",
-        "7z" => "application/x-7z-compressed",
-        _ => "text/plain; charset=utf8",
+/// Parse a `Cookie` header into `(name, value)` pairs, in the order
+/// they were sent. Pairs that aren't `name=value`, or whose name
+/// isn't a valid token, are skipped. Values that can't be percent
+/// decoded are kept as-is.
+pub fn parse_cookies(header: &str) -> Vec<(String, String)> {
+    header
+        .split(';')
+        .filter_map(|pair| {
+            let (name, value) = pair.split_once('=')?;
+            let name = name.trim();
+            if name.is_empty() || !name.bytes().all(is_token_char) {
+                return None;
+            }
+            let value = value.trim();
+            let value = value
+                .strip_prefix('"')
+                .and_then(|v| v.strip_suffix('"'))
+                .unwrap_or(value);
+            let value = percent_decode(value).unwrap_or_else(|| value.to_string());
+            Some((name.to_string(), value))
+        })
+        .collect()
+}
+
+/// Is this byte allowed in an HTTP token, like a header or cookie
+/// name? (RFC 7230)
+pub fn is_token_char(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&b)
+}
+
+/// Percent encode a string for use in a URL or form value. Only
+/// unreserved characters (RFC 3986) are left as-is.
+pub fn percent_encode(inp: &str) -> String {
+    let mut out = String::with_capacity(inp.len());
+    for b in inp.bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                out.push(b as char)
+            }
+            _ => out.push_str(&format!("%{:02X}", b)),
+        }
     }
+    out
+}
+
+/// Lowercase hex encoding of some bytes.
+pub fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// URL-safe base64 encoding without padding (RFC 4648 §5).
+pub fn base64_url(bytes: &[u8]) -> String {
+    base64_encode(
+        bytes,
+        b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_",
+        false,
+    )
+}
+
+/// Standard base64 encoding with padding (RFC 4648 §4).
+pub fn base64(bytes: &[u8]) -> String {
+    base64_encode(
+        bytes,
+        b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/",
+        true,
+    )
+}
+
+fn base64_encode(bytes: &[u8], alphabet: &[u8; 64], pad: bool) -> String {
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = match chunk.len() {
+            3 => (chunk[0] as u32) << 16 | (chunk[1] as u32) << 8 | chunk[2] as u32,
+            2 => (chunk[0] as u32) << 16 | (chunk[1] as u32) << 8,
+            _ => (chunk[0] as u32) << 16,
+        };
+        for i in 0..=chunk.len() {
+            out.push(alphabet[(n >> (18 - i * 6)) as usize & 63] as char);
+        }
+        if pad {
+            for _ in chunk.len()..3 {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+/// Standard base64 decoding. Padding is optional; anything outside
+/// the alphabet returns `None`.
+pub fn base64_decode(s: &str) -> Option<Vec<u8>> {
+    let s = s.trim_end_matches('=').as_bytes();
+    let mut out = Vec::with_capacity(s.len() * 3 / 4);
+    for chunk in s.chunks(4) {
+        if chunk.len() == 1 {
+            return None;
+        }
+        let mut n = 0u32;
+        for (i, &c) in chunk.iter().enumerate() {
+            let v = match c {
+                b'A'..=b'Z' => c - b'A',
+                b'a'..=b'z' => c - b'a' + 26,
+                b'0'..=b'9' => c - b'0' + 52,
+                b'+' => 62,
+                b'/' => 63,
+                _ => return None,
+            };
+            n |= (v as u32) << (18 - i * 6);
+        }
+        for i in 0..chunk.len() - 1 {
+            out.push((n >> (16 - i * 8)) as u8);
+        }
+    }
+    Some(out)
+}
+
+/// Compare two byte strings without bailing early, so the time taken
+/// doesn't leak how much of a secret matched.
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// Does `path` match `pattern`? A pattern ending in `*` matches every
+/// path that starts with it, so `"/admin/*"` matches `"/admin/users"`.
+pub fn path_matches(pattern: &str, path: &str) -> bool {
+    match pattern.strip_suffix('*') {
+        Some(prefix) => path.starts_with(prefix),
+        None => path == pattern,
+    }
+}
+
+/// Content type for a file based on its extension. See the
+/// [`mime`](../mime/index.html) module.
+pub fn content_type(path: &str) -> std::borrow::Cow<'static, str> {
+    crate::mime::from_path(path)
 }
@@ -0,0 +1,41 @@
+use {crate::Error, std::fmt};
+
+/// The HTTP versions Vial speaks.
+#[derive(PartialEq, Eq, Hash, Debug, Clone, Copy, Default)]
+pub enum Version {
+    /// HTTP/1.0
+    HTTP10,
+    /// HTTP/1.1
+    #[default]
+    HTTP11,
+}
+
+impl Version {
+    /// Version as it appears in a request or status line, like
+    /// `"HTTP/1.1"`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Version::HTTP10 => "HTTP/1.0",
+            Version::HTTP11 => "HTTP/1.1",
+        }
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl std::str::FromStr for Version {
+    type Err = Error;
+
+    /// Converts `"HTTP/1.0"` or `"HTTP/1.1"` into our enum.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "HTTP/1.0" => Version::HTTP10,
+            "HTTP/1.1" => Version::HTTP11,
+            _ => return Err(Error::ParseVersion),
+        })
+    }
+}
@@ -0,0 +1,415 @@
+//! WebSockets let the browser and your app send messages back and
+//! forth over a single, long-lived connection.
+//!
+//! Enable the `websocket` feature, then return
+//! [`Response::websocket()`](../struct.Response.html#method.websocket)
+//! from an action. Once the handshake is done, your closure gets its
+//! own thread and a [`WebSocket`](struct.WebSocket.html) to talk to
+//! the client with:
+//!
+//! ```no_run
+//! use vial::{prelude::*, websocket::Message};
+//!
+//! routes! {
+//!     GET "/echo" => |_| Response::websocket(|mut ws| {
+//!         while let Ok(msg) = ws.recv() {
+//!             match msg {
+//!                 Message::Text(text) => ws.send_text(&text).unwrap(),
+//!                 Message::Binary(data) => ws.send_binary(&data).unwrap(),
+//!                 Message::Close(..) => break,
+//!                 _ => {}
+//!             }
+//!         }
+//!     });
+//! }
+//!
+//! fn main() {
+//!     run!().unwrap();
+//! }
+//! ```
+//!
+//! Pings are answered automatically, messages sent in fragments are
+//! put back together, and a close frame is sent when the `WebSocket`
+//! is dropped if one hasn't been sent already.
+use {
+    crate::{listener::Stream, util, Error, Request, Response, Result},
+    sha1::{Digest, Sha1},
+    std::{
+        io::{Read, Write},
+        net::{Shutdown, SocketAddr, TcpStream},
+        thread,
+    },
+};
+
+/// Magic string from RFC 6455 used to build the accept key.
+const GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+/// Largest message we'll put together from the client, in bytes.
+pub const MAX_MESSAGE_SIZE: usize = 16 * 1024 * 1024;
+
+/// Close codes from RFC 6455.
+pub mod close_code {
+    /// The connection did what it was made for.
+    pub const NORMAL: u16 = 1000;
+    /// The server or browser is going away.
+    pub const GOING_AWAY: u16 = 1001;
+    /// The other side broke the WebSocket protocol.
+    pub const PROTOCOL_ERROR: u16 = 1002;
+    /// A text message wasn't valid UTF-8.
+    pub const INVALID_DATA: u16 = 1007;
+    /// A message was bigger than we're willing to accept.
+    pub const TOO_BIG: u16 = 1009;
+}
+
+/// Function that takes over the connection after a successful
+/// handshake.
+pub(crate) type Handler = Box<dyn FnOnce(WebSocket) + Send>;
+
+/// A complete message from the client, or one to send to it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Message {
+    /// UTF-8 text.
+    Text(String),
+    /// Raw bytes.
+    Binary(Vec<u8>),
+    /// Ping. Pings from the client are answered for you.
+    Ping(Vec<u8>),
+    /// Answer to a ping.
+    Pong(Vec<u8>),
+    /// The connection is closing, with an optional code and reason.
+    Close(Option<(u16, String)>),
+}
+
+/// `Sec-WebSocket-Accept` value for the client's `Sec-WebSocket-Key`.
+pub fn accept_key(key: &str) -> String {
+    let mut sha = Sha1::new();
+    sha.update(key.trim().as_bytes());
+    sha.update(GUID.as_bytes());
+    util::base64(&sha.finalize())
+}
+
+/// If `req` is a valid WebSocket upgrade request, returns its
+/// `Sec-WebSocket-Key`.
+pub fn upgrade_key(req: &Request) -> Option<String> {
+    let has_token = |name: &str, token: &str| {
+        req.header(name)
+            .map(|v| v.split(',').any(|t| t.trim().eq_ignore_ascii_case(token)))
+            .unwrap_or(false)
+    };
+    if req.method() != "GET"
+        || !has_token("Upgrade", "websocket")
+        || !has_token("Connection", "upgrade")
+        || req.header("Sec-WebSocket-Version").as_deref() != Some("13")
+    {
+        return None;
+    }
+    req.header("Sec-WebSocket-Key")
+        .map(|key| key.trim().to_string())
+        .filter(|key| !key.is_empty())
+}
+
+/// Finish the handshake for `res` and run its WebSocket handler on a
+/// new thread.
+pub(crate) fn start(mut stream: Stream, key: &str, res: &Response, handler: Handler) -> Result<()> {
+    let mut handshake = format!(
+        "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n",
+        accept_key(key)
+    );
+    if let Some(protocol) = res.header("Sec-WebSocket-Protocol") {
+        handshake.push_str(&format!("Sec-WebSocket-Protocol: {}\r\n", protocol));
+    }
+    handshake.push_str("\r\n");
+    stream.write_all(handshake.as_bytes())?;
+    stream.flush()?;
+
+    let ws = WebSocket::from_stream(stream);
+    thread::spawn(move || handler(ws));
+    Ok(())
+}
+
+/// A single frame read from the client.
+struct Frame {
+    fin: bool,
+    opcode: u8,
+    payload: Vec<u8>,
+}
+
+/// An open WebSocket connection to a client.
+pub struct WebSocket {
+    stream: Stream,
+    /// Opcode and data of a fragmented message being put together.
+    partial: Option<(u8, Vec<u8>)>,
+    close_sent: bool,
+    close_received: bool,
+    max_message_size: usize,
+}
+
+impl WebSocket {
+    /// Wrap a stream that has already finished the handshake.
+    pub fn new(stream: TcpStream) -> WebSocket {
+        WebSocket::from_stream(Stream::from(stream))
+    }
+
+    /// Wrap a connection from any kind of listener.
+    pub(crate) fn from_stream(stream: Stream) -> WebSocket {
+        WebSocket {
+            stream,
+            partial: None,
+            close_sent: false,
+            close_received: false,
+            max_message_size: MAX_MESSAGE_SIZE,
+        }
+    }
+
+    /// Change the largest message we'll accept from the client.
+    /// Defaults to [`MAX_MESSAGE_SIZE`](constant.MAX_MESSAGE_SIZE.html).
+    pub fn set_max_message_size(&mut self, size: usize) {
+        self.max_message_size = size;
+    }
+
+    /// Address of the client.
+    pub fn remote_addr(&self) -> Result<SocketAddr> {
+        Ok(self.stream.peer_addr()?)
+    }
+
+    /// Is the connection closed, or closing?
+    pub fn is_closed(&self) -> bool {
+        self.close_sent || self.close_received
+    }
+
+    /// Wait for the next message from the client. Pings are answered
+    /// and returned, and a close from the client is answered before
+    /// it's returned. Returns `Error::ConnectionClosed` once the
+    /// connection is closed.
+    pub fn recv(&mut self) -> Result<Message> {
+        if self.close_received {
+            return Err(Error::ConnectionClosed);
+        }
+        loop {
+            let frame = self.read_frame()?;
+            match frame.opcode {
+                0x0 => {
+                    let (opcode, mut data) = match self.partial.take() {
+                        Some(partial) => partial,
+                        None => {
+                            return self
+                                .fail(close_code::PROTOCOL_ERROR, "Unexpected continuation frame")
+                        }
+                    };
+                    if data.len() + frame.payload.len() > self.max_message_size {
+                        return self.fail(close_code::TOO_BIG, "Message too big");
+                    }
+                    data.extend_from_slice(&frame.payload);
+                    if frame.fin {
+                        return self.message(opcode, data);
+                    }
+                    self.partial = Some((opcode, data));
+                }
+                0x1 | 0x2 => {
+                    if self.partial.is_some() {
+                        return self
+                            .fail(close_code::PROTOCOL_ERROR, "Expected continuation frame");
+                    }
+                    if frame.fin {
+                        return self.message(frame.opcode, frame.payload);
+                    }
+                    self.partial = Some((frame.opcode, frame.payload));
+                }
+                0x8 => {
+                    self.close_received = true;
+                    let reason = match frame.payload.len() {
+                        0 => None,
+                        1 => return self.fail(close_code::PROTOCOL_ERROR, "Bad close frame"),
+                        _ => {
+                            let code = u16::from_be_bytes([frame.payload[0], frame.payload[1]]);
+                            match String::from_utf8(frame.payload[2..].to_vec()) {
+                                Ok(reason) => Some((code, reason)),
+                                Err(_) => {
+                                    return self.fail(close_code::INVALID_DATA, "Bad close reason")
+                                }
+                            }
+                        }
+                    };
+                    if !self.close_sent {
+                        let code = reason
+                            .as_ref()
+                            .map(|(c, _)| *c)
+                            .unwrap_or(close_code::NORMAL);
+                        self.close(code, "")?;
+                    }
+                    let _ = self.stream.shutdown(Shutdown::Both);
+                    return Ok(Message::Close(reason));
+                }
+                0x9 => {
+                    if !self.close_sent {
+                        self.write_frame(0xA, &frame.payload)?;
+                    }
+                    return Ok(Message::Ping(frame.payload));
+                }
+                0xA => return Ok(Message::Pong(frame.payload)),
+                _ => return self.fail(close_code::PROTOCOL_ERROR, "Unknown opcode"),
+            }
+        }
+    }
+
+    /// Send a message to the client.
+    pub fn send(&mut self, msg: Message) -> Result<()> {
+        match msg {
+            Message::Text(text) => self.send_text(&text),
+            Message::Binary(data) => self.send_binary(&data),
+            Message::Ping(data) => self.ping(&data),
+            Message::Pong(data) => self.control(0xA, &data),
+            Message::Close(Some((code, reason))) => self.close(code, &reason),
+            Message::Close(None) => self.close(close_code::NORMAL, ""),
+        }
+    }
+
+    /// Send a text message.
+    pub fn send_text(&mut self, text: &str) -> Result<()> {
+        self.data(0x1, text.as_bytes())
+    }
+
+    /// Send a binary message.
+    pub fn send_binary(&mut self, data: &[u8]) -> Result<()> {
+        self.data(0x2, data)
+    }
+
+    /// Ping the client. Its pong will show up in
+    /// [`recv()`](#method.recv).
+    pub fn ping(&mut self, data: &[u8]) -> Result<()> {
+        self.control(0x9, data)
+    }
+
+    /// Start closing the connection. Keep calling
+    /// [`recv()`](#method.recv) until you get `Message::Close` back if
+    /// you want to wait for the client to finish.
+    pub fn close(&mut self, code: u16, reason: &str) -> Result<()> {
+        if self.close_sent {
+            return Ok(());
+        }
+        let mut payload = code.to_be_bytes().to_vec();
+        payload.extend_from_slice(reason.as_bytes());
+        payload.truncate(125);
+        self.close_sent = true;
+        self.write_frame(0x8, &payload)
+    }
+
+    /// Send a data frame, unless we're closing.
+    fn data(&mut self, opcode: u8, data: &[u8]) -> Result<()> {
+        if self.close_sent {
+            return Err(Error::ConnectionClosed);
+        }
+        self.write_frame(opcode, data)
+    }
+
+    /// Send a control frame, which can't be more than 125 bytes.
+    fn control(&mut self, opcode: u8, data: &[u8]) -> Result<()> {
+        if data.len() > 125 {
+            return Err(Error::WebSocket(
+                "Control frames must be 125 bytes or less".into(),
+            ));
+        }
+        self.data(opcode, data)
+    }
+
+    /// Turn a finished message's data into a `Message`.
+    fn message(&mut self, opcode: u8, data: Vec<u8>) -> Result<Message> {
+        if opcode == 0x2 {
+            return Ok(Message::Binary(data));
+        }
+        match String::from_utf8(data) {
+            Ok(text) => Ok(Message::Text(text)),
+            Err(_) => self.fail(close_code::INVALID_DATA, "Text message isn't valid UTF-8"),
+        }
+    }
+
+    /// Close the connection because the client did something wrong.
+    fn fail<T>(&mut self, code: u16, reason: &str) -> Result<T> {
+        let _ = self.close(code, reason);
+        self.close_received = true;
+        let _ = self.stream.shutdown(Shutdown::Both);
+        Err(Error::WebSocket(reason.to_string()))
+    }
+
+    /// Read a single frame from the client.
+    fn read_frame(&mut self) -> Result<Frame> {
+        let mut head = [0u8; 2];
+        if let Err(e) = self.stream.read_exact(&mut head) {
+            self.close_received = true;
+            return Err(match e.kind() {
+                std::io::ErrorKind::UnexpectedEof => Error::ConnectionClosed,
+                _ => e.into(),
+            });
+        }
+
+        let fin = head[0] & 0x80 != 0;
+        let opcode = head[0] & 0x0F;
+        if head[0] & 0x70 != 0 {
+            return self.fail(close_code::PROTOCOL_ERROR, "Reserved bits set");
+        }
+        if head[1] & 0x80 == 0 {
+            return self.fail(close_code::PROTOCOL_ERROR, "Client frames must be masked");
+        }
+
+        let len = match head[1] & 0x7F {
+            126 => {
+                let mut len = [0u8; 2];
+                self.stream.read_exact(&mut len)?;
+                u16::from_be_bytes(len) as u64
+            }
+            127 => {
+                let mut len = [0u8; 8];
+                self.stream.read_exact(&mut len)?;
+                u64::from_be_bytes(len)
+            }
+            len => len as u64,
+        };
+        if opcode >= 0x8 && (len > 125 || !fin) {
+            return self.fail(close_code::PROTOCOL_ERROR, "Bad control frame");
+        }
+        if len > self.max_message_size as u64 {
+            return self.fail(close_code::TOO_BIG, "Message too big");
+        }
+
+        let mut mask = [0u8; 4];
+        self.stream.read_exact(&mut mask)?;
+        let mut payload = vec![0u8; len as usize];
+        self.stream.read_exact(&mut payload)?;
+        for (i, b) in payload.iter_mut().enumerate() {
+            *b ^= mask[i % 4];
+        }
+
+        Ok(Frame {
+            fin,
+            opcode,
+            payload,
+        })
+    }
+
+    /// Write a single, unmasked frame to the client.
+    fn write_frame(&mut self, opcode: u8, payload: &[u8]) -> Result<()> {
+        let mut frame = Vec::with_capacity(payload.len() + 10);
+        frame.push(0x80 | opcode);
+        if payload.len() < 126 {
+            frame.push(payload.len() as u8);
+        } else if payload.len() <= u16::MAX as usize {
+            frame.push(126);
+            frame.extend_from_slice(&(payload.len() as u16).to_be_bytes());
+        } else {
+            frame.push(127);
+            frame.extend_from_slice(&(payload.len() as u64).to_be_bytes());
+        }
+        frame.extend_from_slice(payload);
+        self.stream.write_all(&frame)?;
+        self.stream.flush()?;
+        Ok(())
+    }
+}
+
+impl Drop for WebSocket {
+    fn drop(&mut self) {
+        if !self.close_sent {
+            let _ = self.close(close_code::NORMAL, "");
+        }
+    }
+}
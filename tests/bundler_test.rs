@@ -4,7 +4,7 @@ use vial::bundler::walk;
 fn test_walk() {
     assert_eq!(6, walk("tests/assets/").count());
 
-    let expected = vec![
+    let expected = [
         "dinner.jpg",
         "letter.jpg",
         "puff.gif",
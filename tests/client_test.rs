@@ -0,0 +1,195 @@
+use vial::{test::Client, Router};
+
+mod app {
+    use vial::prelude::*;
+
+    routes! {
+        #![filter(secret)]
+
+        GET "/" => |_| "Hello, world!";
+        GET "/hello/:name" => |req| format!("Hello, {}!", req.arg("name").unwrap_or("?"));
+        GET "/agent" => |req| req.header("User-Agent").unwrap_or_default().to_string();
+        POST "/echo" => |req| req.form("echo").unwrap_or("?").to_string();
+        PUT "/body" => |req| req.body().to_string();
+        GET "/boom" => boom;
+
+        #[filter(deny)]
+        GET "/private" => |_| "Top secret!";
+    }
+
+    fn boom(_: Request) -> Response {
+        panic!("boom!")
+    }
+
+    fn secret(req: &mut Request) -> Option<Response> {
+        if req.query("secret").is_some() {
+            Some(Response::from_text("Found it."))
+        } else {
+            None
+        }
+    }
+
+    fn deny(_: &mut Request) -> Option<Response> {
+        Some(Response::from(403))
+    }
+
+    #[cfg(feature = "cookies")]
+    pub mod cookies {
+        use vial::prelude::*;
+
+        routes! {
+            GET "/cookie" => |req| req.cookie("count").unwrap_or("0").to_string();
+            GET "/cookie/set" => |_| Response::from_cookie("count", "5").with_cookie("SessionID", "abc");
+            GET "/cookie/remove" => |_| Response::new().without_cookie("count");
+        }
+    }
+
+    #[cfg(feature = "sessions")]
+    pub mod sessions {
+        use vial::prelude::*;
+
+        routes! {
+            GET "/session" => |req| req.session("name").unwrap_or("nobody").to_string();
+            GET "/session/set" => |req| Response::from_session("name", req.query("name").unwrap_or("?"));
+            GET "/session/count" => |req| {
+                let count = req.session("count").unwrap_or("0").parse::<usize>().unwrap_or(0) + 1;
+                Response::from_session("count", &count.to_string()).with_body(count.to_string())
+            };
+            GET "/session/logout" => |_| Response::new().with_cleared_session();
+        }
+    }
+}
+
+fn client() -> Client {
+    let mut router = Router::new();
+    app::vial_add_to_router(&mut router);
+    #[cfg(feature = "cookies")]
+    app::cookies::vial_add_to_router(&mut router);
+    #[cfg(feature = "sessions")]
+    app::sessions::vial_add_to_router(&mut router);
+    Client::new(router)
+}
+
+#[test]
+fn get() {
+    let client = client();
+
+    let res = client.get("/").send();
+    assert_eq!(200, res.code());
+    assert_eq!("Hello, world!", res.body());
+
+    let res = client.get("/hello/Alice").send();
+    assert_eq!("Hello, Alice!", res.body());
+
+    let res = client.get("/nowhere").send();
+    assert_eq!(404, res.code());
+}
+
+#[test]
+fn headers_and_bodies() {
+    let client = client();
+
+    let res = client
+        .get("/agent")
+        .header("User-Agent", "vial-test")
+        .send();
+    assert_eq!("vial-test", res.body());
+
+    let res = client
+        .post("/echo")
+        .form("echo", "Well, that's just great!")
+        .send();
+    assert_eq!("Well, that's just great!", res.body());
+
+    let res = client.put("/body").body("raw body").send();
+    assert_eq!("raw body", res.body());
+    assert_eq!(b"raw body", res.body_bytes());
+}
+
+#[test]
+fn filters() {
+    let client = client();
+
+    let res = client.get("/?secret=1").send();
+    assert_eq!("Found it.", res.body());
+    assert_eq!("text/plain; charset=utf8", res.content_type());
+
+    let res = client.get("/private").send();
+    assert_eq!(403, res.code());
+}
+
+#[test]
+fn panics() {
+    let res = client().get("/boom").send();
+    assert_eq!(500, res.code());
+}
+
+#[test]
+fn assets() {
+    vial::asset_dir!("./tests/assets/");
+    let client = client();
+
+    let res = client.get("/xiii.txt").send();
+    assert_eq!(200, res.code());
+    assert!(res.body().starts_with("O! that you were your self"));
+    assert_eq!(
+        std::fs::read("tests/assets/dinner.jpg").unwrap(),
+        client.get("/dinner.jpg").send().body_bytes()
+    );
+
+    let etag = res.header("ETag").unwrap();
+    let res = client.get("/xiii.txt").header("If-None-Match", etag).send();
+    assert_eq!(304, res.code());
+}
+
+#[test]
+#[cfg(feature = "cookies")]
+fn cookies() {
+    let client = client();
+    assert_eq!("0", client.get("/cookie").send().body());
+
+    client.get("/cookie/set").send();
+    assert_eq!(Some("5".to_string()), client.cookie("count"));
+    assert_eq!(Some("abc".to_string()), client.cookie("SessionID"));
+    assert_eq!(None, client.cookie("sessionid"));
+    assert_eq!("5", client.get("/cookie").send().body());
+
+    client.get("/cookie/remove").send();
+    assert_eq!(None, client.cookie("count"));
+    assert_eq!("0", client.get("/cookie").send().body());
+}
+
+#[test]
+#[cfg(feature = "sessions")]
+fn sessions() {
+    let client = client();
+    assert_eq!("nobody", client.get("/session").send().body());
+
+    client.get("/session/set?name=Roger").send();
+    assert_eq!("Roger", client.get("/session").send().body());
+
+    client.clear_cookies();
+    assert_eq!("nobody", client.get("/session").send().body());
+
+    // every value lives in one cookie, and setting one keeps the rest
+    client.get("/session/set?name=Alice").send();
+    assert_eq!("1", client.get("/session/count").send().body());
+    assert_eq!("2", client.get("/session/count").send().body());
+    assert_eq!("Alice", client.get("/session").send().body());
+
+    // a bad cookie is treated like an empty session
+    client.clear_cookies();
+    assert_eq!(
+        "nobody",
+        client
+            .get("/session")
+            .cookie("__vial_session", "garbage")
+            .send()
+            .body()
+    );
+
+    client.get("/session/set?name=Alice").send();
+    client.get("/session/logout").send();
+    assert_eq!(None, client.cookie("__vial_session"));
+    assert_eq!("nobody", client.get("/session").send().body());
+}
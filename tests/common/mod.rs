@@ -0,0 +1,85 @@
+//! Helpers for tests that talk to a real server over a socket, for
+//! the things `vial::test::Client` can't do, like sending malformed
+//! requests or holding connections open.
+#![allow(dead_code)]
+
+use {
+    std::{
+        io::{self, Read, Write},
+        net::TcpStream,
+        thread,
+        time::Duration,
+    },
+    vial::{Bind, Router},
+};
+
+/// A router with the routes added by a `routes!` block's
+/// `vial_add_to_router`.
+pub fn router(routes: fn(&mut Router)) -> Router {
+    let mut router = Router::new();
+    routes(&mut router);
+    router
+}
+
+/// Serve `router` on `addr` in the background, returning the address
+/// of the first socket. Use port `0` to get a free one.
+pub fn server<T: Bind>(addr: T, router: Router) -> String {
+    let listeners = addr.bind().unwrap();
+    let addr = listeners[0].to_string().replace("http://", "");
+    thread::spawn(move || vial::run(listeners, router, Some("")).unwrap());
+    addr
+}
+
+/// Connect to `addr`, waiting for the server to come up.
+pub fn connect(addr: &str) -> TcpStream {
+    loop {
+        if let Ok(client) = try_connect(addr) {
+            return client;
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+}
+
+/// Connect to `addr` once, with a read timeout so a stuck server fails
+/// the test instead of hanging it.
+pub fn try_connect(addr: &str) -> io::Result<TcpStream> {
+    let client = TcpStream::connect(addr)?;
+    client.set_read_timeout(Some(Duration::from_secs(5)))?;
+    Ok(client)
+}
+
+/// Read up to the end of a response's head, or the `100 Continue`
+/// before it. Stops early if the server drops the connection.
+pub fn read_head<R: Read>(client: &mut R) -> String {
+    let mut head = vec![];
+    let mut byte = [0u8; 1];
+    while !head.ends_with(b"\r\n\r\n") {
+        match client.read(&mut byte) {
+            Ok(1) => head.push(byte[0]),
+            _ => break,
+        }
+    }
+    String::from_utf8(head).unwrap()
+}
+
+/// Read a response's body, once its head has been read.
+pub fn read_body<R: Read>(client: &mut R, head: &str) -> String {
+    let len = head
+        .lines()
+        .find_map(|line| line.strip_prefix("Content-Length: "))
+        .and_then(|len| len.trim().parse().ok())
+        .unwrap_or(0);
+    let mut body = vec![0; len];
+    client.read_exact(&mut body).unwrap();
+    String::from_utf8(body).unwrap()
+}
+
+/// Send a raw request to `addr`, returning the response's head and
+/// body.
+pub fn send(addr: &str, raw: &str) -> (String, String) {
+    let mut client = connect(addr);
+    client.write_all(raw.as_bytes()).unwrap();
+    let head = read_head(&mut client);
+    let body = read_body(&mut client, &head);
+    (head, body)
+}
@@ -0,0 +1,108 @@
+use {
+    std::time::Duration,
+    vial::{
+        cors::{self, Cors},
+        test::Client,
+        Router,
+    },
+};
+
+mod app {
+    vial::routes! {
+        GET "/api/hello" => |_| "Hello, world!";
+        POST "/api/hello" => |_| "Posted!";
+    }
+}
+
+fn client() -> Client {
+    cors::enable(
+        Cors::new()
+            .allow_origin("https://app.example.com")
+            .allow_header("Content-Type")
+            .expose_header("X-Total")
+            .allow_credentials(true)
+            .max_age(Duration::from_secs(600)),
+    );
+    let mut router = Router::new();
+    app::vial_add_to_router(&mut router);
+    Client::new(router)
+}
+
+#[test]
+fn preflight() {
+    let client = client();
+    let res = client
+        .options("/api/hello")
+        .header("Origin", "https://app.example.com")
+        .header("Access-Control-Request-Method", "POST")
+        .header("Access-Control-Request-Headers", "content-type")
+        .send();
+    assert_eq!(204, res.code());
+    assert_eq!(
+        Some("https://app.example.com"),
+        res.header("Access-Control-Allow-Origin")
+    );
+    assert_eq!(
+        Some("GET, HEAD, POST, PUT, PATCH, DELETE"),
+        res.header("Access-Control-Allow-Methods")
+    );
+    assert_eq!(
+        Some("Content-Type"),
+        res.header("Access-Control-Allow-Headers")
+    );
+    assert_eq!(Some("true"), res.header("Access-Control-Allow-Credentials"));
+    assert_eq!(Some("600"), res.header("Access-Control-Max-Age"));
+
+    // answered even for paths without a route
+    let res = client
+        .options("/api/elsewhere")
+        .header("Origin", "https://app.example.com")
+        .header("Access-Control-Request-Method", "DELETE")
+        .send();
+    assert_eq!(204, res.code());
+
+    let res = client
+        .options("/api/hello")
+        .header("Origin", "https://evil.example.com")
+        .header("Access-Control-Request-Method", "POST")
+        .send();
+    assert_eq!(403, res.code());
+    assert_eq!(None, res.header("Access-Control-Allow-Origin"));
+
+    let res = client
+        .options("/api/hello")
+        .header("Origin", "https://app.example.com")
+        .header("Access-Control-Request-Method", "TRACE")
+        .send();
+    assert_eq!(403, res.code());
+
+    // a plain OPTIONS request isn't a preflight
+    assert_eq!(404, client.options("/api/hello").send().code());
+}
+
+#[test]
+fn actual_requests() {
+    let client = client();
+    let res = client
+        .post("/api/hello")
+        .header("Origin", "https://app.example.com")
+        .send();
+    assert_eq!("Posted!", res.body());
+    assert_eq!(
+        Some("https://app.example.com"),
+        res.header("Access-Control-Allow-Origin")
+    );
+    assert_eq!(Some("Origin"), res.header("Vary"));
+    assert_eq!(Some("X-Total"), res.header("Access-Control-Expose-Headers"));
+    assert_eq!(Some("true"), res.header("Access-Control-Allow-Credentials"));
+
+    let res = client
+        .get("/api/hello")
+        .header("Origin", "https://evil.example.com")
+        .send();
+    assert_eq!("Hello, world!", res.body());
+    assert_eq!(None, res.header("Access-Control-Allow-Origin"));
+
+    let res = client.get("/api/hello").send();
+    assert_eq!(None, res.header("Access-Control-Allow-Origin"));
+}
@@ -0,0 +1,54 @@
+use vial::{
+    cors::{self, Cors},
+    test::Client,
+    Router,
+};
+
+mod app {
+    vial::routes! {
+        GET "/api/hello" => |_| "Hello, world!";
+    }
+}
+
+fn client() -> Client {
+    cors::enable(
+        Cors::new()
+            .allow_origin("https://app.example.com")
+            .allow_any_origin()
+            .allow_credentials(true),
+    );
+    let mut router = Router::new();
+    app::vial_add_to_router(&mut router);
+    Client::new(router)
+}
+
+#[test]
+fn wildcard_never_gets_credentials() {
+    let client = client();
+    let res = client
+        .get("/api/hello")
+        .header("Origin", "https://evil.example.com")
+        .send();
+    assert_eq!(Some("*"), res.header("Access-Control-Allow-Origin"));
+    assert_eq!(None, res.header("Access-Control-Allow-Credentials"));
+
+    let res = client
+        .options("/api/hello")
+        .header("Origin", "https://evil.example.com")
+        .header("Access-Control-Request-Method", "GET")
+        .send();
+    assert_eq!(204, res.code());
+    assert_eq!(Some("*"), res.header("Access-Control-Allow-Origin"));
+    assert_eq!(None, res.header("Access-Control-Allow-Credentials"));
+
+    // origins listed by name still do
+    let res = client
+        .get("/api/hello")
+        .header("Origin", "https://app.example.com")
+        .send();
+    assert_eq!(
+        Some("https://app.example.com"),
+        res.header("Access-Control-Allow-Origin")
+    );
+    assert_eq!(Some("true"), res.header("Access-Control-Allow-Credentials"));
+}
@@ -0,0 +1,91 @@
+#![cfg(feature = "csrf")]
+
+use vial::{test::Client, Router};
+
+mod app {
+    use vial::{csrf::protect, prelude::*};
+
+    routes! {
+        #![filter(protect)]
+
+        GET "/" => |req| format!("<form method='POST'>{}</form>", req.csrf_field());
+        GET "/token" => |req| req.csrf_token();
+        POST "/echo" => |req| req.form("echo").unwrap_or("?").to_string();
+        DELETE "/thing" => |_| "Deleted.";
+
+        #[skip(protect)]
+        POST "/hooks/github" => |_| "Hooked.";
+
+        #[filter(noop)]
+        #[skip(protect)]
+        POST "/hooks/gitlab" => |_| "Hooked.";
+
+        GET "/logout" => |_| Response::new().with_cleared_session();
+    }
+
+    fn noop(_: &mut Request) -> Option<Response> {
+        None
+    }
+}
+
+fn client() -> Client {
+    let mut router = Router::new();
+    app::vial_add_to_router(&mut router);
+    Client::new(router)
+}
+
+#[test]
+fn rejects_missing_token() {
+    let client = client();
+    let res = client.post("/echo").form("echo", "hi").send();
+    assert_eq!(403, res.code());
+
+    // a token has to match the session's, not just exist
+    client.get("/token").send();
+    let res = client
+        .post("/echo")
+        .form("echo", "hi")
+        .form("csrf_token", "guess")
+        .send();
+    assert_eq!(403, res.code());
+    assert_eq!(403, client.delete("/thing").send().code());
+}
+
+#[test]
+fn accepts_valid_token() {
+    let client = client();
+    let token = client.get("/token").send().body().to_string();
+    assert!(!token.is_empty());
+    assert_eq!(token, client.get("/token").send().body());
+    assert!(client.get("/").send().body().contains(&token));
+
+    let res = client
+        .post("/echo")
+        .form("echo", "hi")
+        .form("csrf_token", &token)
+        .send();
+    assert_eq!(200, res.code());
+    assert_eq!("hi", res.body());
+
+    let res = client
+        .delete("/thing")
+        .header("X-CSRF-Token", &token)
+        .send();
+    assert_eq!("Deleted.", res.body());
+
+    // a new session means a new token
+    client.get("/logout").send();
+    let res = client.post("/echo").form("csrf_token", &token).send();
+    assert_eq!(403, res.code());
+    assert_ne!(token, client.get("/token").send().body());
+}
+
+#[test]
+fn skipped_routes() {
+    let client = client();
+    assert_eq!(200, client.get("/").send().code());
+    assert_eq!("Hooked.", client.post("/hooks/github").send().body());
+    assert_eq!("Hooked.", client.post("/hooks/gitlab").send().body());
+    // other routes are still protected
+    assert_eq!(403, client.post("/echo").send().code());
+}
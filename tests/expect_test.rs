@@ -0,0 +1,149 @@
+mod common;
+
+use {
+    common::{connect, read_body, read_head, router, server},
+    std::io::Write,
+    vial::{
+        limits::{self, Limits},
+        Listener, Request, Response,
+    },
+};
+
+mod app {
+    use vial::prelude::*;
+
+    routes! {
+        POST "/upload" => |req| format!("got {}", req.body());
+
+        #[filter(no_secrets)]
+        POST "/notes" => |req| format!("noted {}", req.body());
+    }
+
+    /// Route filters run once the body is in, so they can look at it.
+    fn no_secrets(req: &mut Request) -> Option<Response> {
+        if req.body().contains("secret") {
+            Some(Response::from(403).with_body("403 Forbidden"))
+        } else {
+            None
+        }
+    }
+}
+
+fn members_only(req: &mut Request) -> Option<Response> {
+    if req.header("X-Member").is_some() {
+        None
+    } else {
+        Some(Response::from(403).with_body("403 Forbidden"))
+    }
+}
+
+/// Listener filters run after the body is read too.
+fn no_spam(req: &mut Request) -> Option<Response> {
+    if req.body() == "spam" {
+        Some(Response::from(403).with_body("403 Forbidden"))
+    } else {
+        None
+    }
+}
+
+/// A listener that checks membership before the body and spam after.
+fn listener() -> Listener {
+    limits::set(Limits::new().max_body_size(100));
+    Listener::tcp("127.0.0.1:0")
+        .unwrap()
+        .filter_head(members_only)
+        .filter(no_spam)
+}
+
+fn upload_head(version: &str, expect: &str, len: usize) -> String {
+    format!(
+        "POST /upload {}\r\nHost: localhost\r\nX-Member: yes\r\nExpect: {}\r\nContent-Length: {}\r\n\r\n",
+        version, expect, len
+    )
+}
+
+#[test]
+fn sends_continue_before_body() {
+    let mut client = connect(&server(listener(), router(app::vial_add_to_router)));
+    client
+        .write_all(upload_head("HTTP/1.1", "100-continue", 5).as_bytes())
+        .unwrap();
+    assert_eq!("HTTP/1.1 100 Continue\r\n\r\n", read_head(&mut client));
+
+    client.write_all(b"hello").unwrap();
+    let head = read_head(&mut client);
+    assert!(head.starts_with("HTTP/1.1 200 "), "{}", head);
+    assert_eq!("got hello", read_body(&mut client, &head));
+}
+
+#[test]
+fn refuses_large_uploads_up_front() {
+    let mut client = connect(&server(listener(), router(app::vial_add_to_router)));
+    client
+        .write_all(upload_head("HTTP/1.1", "100-continue", 5000).as_bytes())
+        .unwrap();
+    let head = read_head(&mut client);
+    assert!(head.starts_with("HTTP/1.1 413 "), "{}", head);
+    assert_eq!("413 Payload Too Large", read_body(&mut client, &head));
+}
+
+#[test]
+fn refuses_filtered_uploads_up_front() {
+    let mut client = connect(&server(listener(), router(app::vial_add_to_router)));
+    client
+        .write_all(b"POST /upload HTTP/1.1\r\nExpect: 100-continue\r\nContent-Length: 5\r\n\r\n")
+        .unwrap();
+    let head = read_head(&mut client);
+    assert!(head.starts_with("HTTP/1.1 403 "), "{}", head);
+}
+
+#[test]
+fn route_filters_see_the_body() {
+    let mut client = connect(&server(listener(), router(app::vial_add_to_router)));
+    client
+        .write_all(
+            b"POST /notes HTTP/1.1\r\nX-Member: yes\r\nExpect: 100-continue\r\nContent-Length: 9\r\n\r\n",
+        )
+        .unwrap();
+    assert_eq!("HTTP/1.1 100 Continue\r\n\r\n", read_head(&mut client));
+
+    client.write_all(b"my secret").unwrap();
+    let head = read_head(&mut client);
+    assert!(head.starts_with("HTTP/1.1 403 "), "{}", head);
+    assert_eq!("403 Forbidden", read_body(&mut client, &head));
+}
+
+#[test]
+fn listener_filters_see_the_body() {
+    let mut client = connect(&server(listener(), router(app::vial_add_to_router)));
+    client
+        .write_all(upload_head("HTTP/1.1", "100-continue", 4).as_bytes())
+        .unwrap();
+    assert_eq!("HTTP/1.1 100 Continue\r\n\r\n", read_head(&mut client));
+
+    client.write_all(b"spam").unwrap();
+    let head = read_head(&mut client);
+    assert!(head.starts_with("HTTP/1.1 403 "), "{}", head);
+}
+
+#[test]
+fn unknown_expectation() {
+    let mut client = connect(&server(listener(), router(app::vial_add_to_router)));
+    client
+        .write_all(upload_head("HTTP/1.1", "something-else", 5).as_bytes())
+        .unwrap();
+    let head = read_head(&mut client);
+    assert!(head.starts_with("HTTP/1.1 417 "), "{}", head);
+}
+
+#[test]
+fn ignored_for_http10() {
+    let mut client = connect(&server(listener(), router(app::vial_add_to_router)));
+    client
+        .write_all(upload_head("HTTP/1.0", "100-continue", 5).as_bytes())
+        .unwrap();
+    client.write_all(b"hello").unwrap();
+    let head = read_head(&mut client);
+    assert!(head.starts_with("HTTP/1.0 200 "), "{}", head);
+    assert_eq!("got hello", read_body(&mut client, &head));
+}
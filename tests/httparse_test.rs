@@ -2498,7 +2498,8 @@ test! {
     b"GET https://example.com:443/ HTTP/1.1\r\nHost: \r\n\r\n",
     |req| {
         assert_eq!(req.method(), "GET");
-        assert_eq!(req.full_path(), "https://example.com:443/");
+        assert_eq!(req.full_path(), "/");
+        assert_eq!(req.host().unwrap(), "example.com:443");
         assert_eq!(req.header("Host").unwrap(), "");
     }
 }
@@ -2813,16 +2814,16 @@ test! {
 
 #[test]
 fn test_request_partial() {
-    match parse(b"GET / HTTP/1.1\r\n\r".to_vec()) {
-        Ok(Status::Partial(..)) => assert!(true),
-        _ => assert!(false),
-    }
+    assert!(matches!(
+        parse(b"GET / HTTP/1.1\r\n\r".to_vec()),
+        Ok(Status::Partial(..))
+    ));
 }
 
 #[test]
 fn test_request_partial_version() {
-    match parse(b"GET / HTTP/1.".to_vec()) {
-        Ok(Status::Partial(..)) => assert!(true),
-        _ => assert!(false),
-    }
+    assert!(matches!(
+        parse(b"GET / HTTP/1.".to_vec()),
+        Ok(Status::Partial(..))
+    ));
 }
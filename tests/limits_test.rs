@@ -0,0 +1,68 @@
+mod common;
+
+use {
+    common::{connect, read_head, router, server},
+    std::{io::Write, thread, time::Duration},
+    vial::limits::{self, Limits},
+};
+
+mod app {
+    use vial::prelude::*;
+
+    routes! {
+        GET "/" => |_| "Hello";
+        POST "/" => |req| req.body().to_string();
+    }
+}
+
+fn short_timeouts() {
+    limits::set(
+        Limits::new()
+            .header_timeout(Duration::from_millis(300))
+            .body_timeout(Duration::from_millis(300)),
+    );
+}
+
+#[test]
+fn header_timeout() {
+    short_timeouts();
+    let mut client = connect(&server("127.0.0.1:0", router(app::vial_add_to_router)));
+    client.write_all(b"GET / HTTP/1.1\r\nHost: ").unwrap();
+    assert!(read_head(&mut client).starts_with("HTTP/1.1 408 "));
+}
+
+#[test]
+fn header_deadline_is_total() {
+    // trickling bytes in doesn't reset the clock
+    short_timeouts();
+    let mut client = connect(&server("127.0.0.1:0", router(app::vial_add_to_router)));
+    for b in b"GET / HTTP/1.1\r\nX-Slow: abcdefghij" {
+        if client.write_all(&[*b]).is_err() {
+            break;
+        }
+        thread::sleep(Duration::from_millis(20));
+    }
+    assert!(read_head(&mut client).starts_with("HTTP/1.1 408 "));
+}
+
+#[test]
+fn body_timeout() {
+    short_timeouts();
+    let mut client = connect(&server("127.0.0.1:0", router(app::vial_add_to_router)));
+    client
+        .write_all(b"POST / HTTP/1.1\r\nHost: localhost\r\nContent-Length: 10\r\n\r\nab")
+        .unwrap();
+    assert!(read_head(&mut client).starts_with("HTTP/1.1 408 "));
+}
+
+#[test]
+fn in_time() {
+    short_timeouts();
+    let mut client = connect(&server("127.0.0.1:0", router(app::vial_add_to_router)));
+    client
+        .write_all(b"POST / HTTP/1.1\r\nHost: localhost\r\nContent-Length: 5\r\n\r\nab")
+        .unwrap();
+    thread::sleep(Duration::from_millis(100));
+    client.write_all(b"cde").unwrap();
+    assert!(read_head(&mut client).starts_with("HTTP/1.1 200 "));
+}
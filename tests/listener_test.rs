@@ -0,0 +1,72 @@
+#![cfg(unix)]
+
+mod common;
+
+use {
+    common::{read_body, read_head, router, server},
+    std::{
+        env, fs,
+        io::Write,
+        os::unix::{fs::PermissionsExt, net::UnixStream},
+        path::PathBuf,
+    },
+    vial::{Listener, ListenerKind},
+};
+
+mod app {
+    use vial::prelude::*;
+
+    routes! {
+        GET "/" => |req| format!("{:?} {}", req.listener(), req.remote_addr());
+    }
+}
+
+fn socket_path(name: &str) -> PathBuf {
+    env::temp_dir().join(format!("vial-{}-{}.sock", name, std::process::id()))
+}
+
+#[test]
+fn unix_socket() {
+    let path = socket_path("unix");
+    let listener = Listener::unix(&path).unwrap();
+    assert_eq!(ListenerKind::Unix, listener.kind());
+    assert_eq!(format!("unix:{}", path.display()), listener.to_string());
+    listener.set_permissions(0o600).unwrap();
+    assert_eq!(
+        0o600,
+        fs::metadata(&path).unwrap().permissions().mode() & 0o777
+    );
+
+    server(listener, router(app::vial_add_to_router));
+    let mut client = UnixStream::connect(&path).unwrap();
+    client
+        .write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\n\r\n")
+        .unwrap();
+    let head = read_head(&mut client);
+    assert!(head.starts_with("HTTP/1.1 200 "));
+    assert_eq!("Unix 127.0.0.1:0", read_body(&mut client, &head));
+}
+
+#[test]
+fn replaces_stale_socket() {
+    let path = socket_path("stale");
+    drop(Listener::unix(&path).unwrap());
+    assert!(path.exists());
+    let listener = Listener::unix(&path).unwrap();
+    assert!(UnixStream::connect(&path).is_ok());
+    drop(listener);
+    fs::remove_file(&path).unwrap();
+
+    // but other files are left alone
+    fs::write(&path, "important").unwrap();
+    assert!(Listener::unix(&path).is_err());
+    assert_eq!("important", fs::read_to_string(&path).unwrap());
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn tcp_listener() {
+    let listener = Listener::tcp("127.0.0.1:0").unwrap();
+    assert_eq!(ListenerKind::Tcp, listener.kind());
+    assert!(listener.to_string().starts_with("http://127.0.0.1:"));
+}
@@ -0,0 +1,64 @@
+mod common;
+
+use {
+    common::{router, send, server},
+    vial::{Listener, Request, Response, Router},
+};
+
+mod app {
+    use vial::prelude::*;
+
+    routes! {
+        GET "/" => |req| req.listener_name().unwrap_or("none").to_string();
+        GET "/admin/stats" => |_| "stats";
+    }
+}
+
+fn admin_only(req: &mut Request) -> Option<Response> {
+    if req.header("X-Admin-Token").as_deref() == Some("secret") {
+        None
+    } else {
+        Some(Response::from(403).with_body("Forbidden"))
+    }
+}
+
+/// Send a request, returning the status code and body.
+fn get(addr: &str, path: &str, headers: &str) -> (String, String) {
+    let raw = format!(
+        "GET {} HTTP/1.1\r\nHost: localhost\r\n{}\r\n",
+        path, headers
+    );
+    let (head, body) = send(addr, &raw);
+    (head[9..12].to_string(), body)
+}
+
+#[test]
+fn serves_on_several_listeners() {
+    let public = Listener::tcp("127.0.0.1:0").unwrap().except("/admin/*");
+    let admin = Listener::tcp("127.0.0.1:0")
+        .unwrap()
+        .named("admin")
+        .only("/admin/*")
+        .only("/")
+        .filter(admin_only);
+    let admin_addr = admin.to_string().replace("http://", "");
+    let public_addr = server(vec![public, admin], router(app::vial_add_to_router));
+
+    let token = "X-Admin-Token: secret\r\n";
+    assert_eq!(("200".into(), "none".into()), get(&public_addr, "/", ""));
+    assert_eq!("404", get(&public_addr, "/admin/stats", token).0);
+
+    assert_eq!(("200".into(), "admin".into()), get(&admin_addr, "/", token));
+    assert_eq!(
+        ("200".into(), "stats".into()),
+        get(&admin_addr, "/admin/stats", token)
+    );
+    assert_eq!("403", get(&admin_addr, "/admin/stats", "").0);
+    assert_eq!("404", get(&admin_addr, "/elsewhere", token).0);
+}
+
+#[test]
+fn empty_listener_list() {
+    let router = Router::new();
+    assert!(vial::run(Vec::<Listener>::new(), router, Some("")).is_err());
+}
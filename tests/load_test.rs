@@ -0,0 +1,61 @@
+mod common;
+
+use {
+    common::{connect, read_head, router, server},
+    std::{io::Write, net::TcpStream, thread, time::Duration},
+    vial::limits::{self, Limits},
+};
+
+mod app {
+    use vial::prelude::*;
+
+    routes! {
+        GET "/" => |_| "Hello";
+    }
+}
+
+fn get(client: &mut TcpStream) -> String {
+    client
+        .write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\n\r\n")
+        .unwrap();
+    read_head(client)
+}
+
+// One test, since the limits are global and each step depends on the
+// connections the last one left open.
+#[test]
+fn load_shedding() {
+    limits::set(Limits::new().max_connections_per_ip(2));
+    let addr = server("127.0.0.1:0", router(app::vial_add_to_router));
+
+    // per IP limit
+    let first = connect(&addr);
+    let second = connect(&addr);
+    thread::sleep(Duration::from_millis(100));
+    let head = get(&mut connect(&addr));
+    assert!(head.starts_with("HTTP/1.1 503 "));
+    assert!(head.contains("\r\nRetry-After: 5\r\n"));
+
+    // closed connections don't count
+    drop(first);
+    drop(second);
+    thread::sleep(Duration::from_millis(100));
+    let head = get(&mut connect(&addr));
+    assert!(head.starts_with("HTTP/1.1 200 "));
+
+    // full queue: tie up every thread, then fill the queue
+    limits::set(
+        Limits::new()
+            .max_pending(1)
+            .retry_after(Duration::from_secs(30)),
+    );
+    let mut idle = vec![];
+    for _ in 0..11 {
+        idle.push(connect(&addr));
+        thread::sleep(Duration::from_millis(50));
+    }
+    thread::sleep(Duration::from_millis(100));
+    let head = get(&mut connect(&addr));
+    assert!(head.starts_with("HTTP/1.1 503 "));
+    assert!(head.contains("\r\nRetry-After: 30\r\n"));
+}
@@ -0,0 +1,77 @@
+use {
+    std::{env, fs},
+    vial::{mime, Response},
+};
+
+#[test]
+fn built_in_types() {
+    assert_eq!("application/wasm", mime::from_path("/pkg/app_bg.wasm"));
+    assert_eq!("video/mp4", mime::from_path("intro.MP4"));
+    assert_eq!("image/avif", mime::from_path("photo.avif"));
+    assert_eq!("application/json", mime::from_path("app.js.map"));
+    assert_eq!("application/gzip", mime::from_path("backup.tar.gz"));
+    assert_eq!("application/octet-stream", mime::from_path("data.unknown"));
+    assert_eq!("application/octet-stream", mime::from_path("v1.2/LICENSE"));
+    assert_eq!(None, mime::from_extension("nope"));
+}
+
+#[test]
+fn add_types() {
+    mime::add(".GMI", "text/gemini");
+    mime::add("xml", "text/xml");
+    assert_eq!("text/gemini", mime::from_path("index.gmi"));
+    assert_eq!(Some("text/xml".into()), mime::from_extension("XML"));
+    assert_eq!("text/xml", mime::from_path("feed.xml"));
+}
+
+#[test]
+fn sniff() {
+    assert_eq!(
+        Some("image/png"),
+        mime::sniff(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR")
+    );
+    assert_eq!(Some("application/wasm"), mime::sniff(b"\0asm\x01\0\0\0"));
+    assert_eq!(Some("image/webp"), mime::sniff(b"RIFF\x24\0\0\0WEBPVP8 "));
+    assert_eq!(
+        Some("image/avif"),
+        mime::sniff(b"\0\0\0\x1cftypavif\0\0\0\0")
+    );
+    assert_eq!(
+        Some("video/mp4"),
+        mime::sniff(b"\0\0\0\x20ftypisom\0\0\x02\0")
+    );
+    assert_eq!(
+        Some("text/html; charset=utf8"),
+        mime::sniff(b"\n  <!DOCTYPE html><html></html>")
+    );
+    assert_eq!(
+        Some("image/svg+xml"),
+        mime::sniff(b"<?xml version=\"1.0\"?><svg>")
+    );
+    assert_eq!(
+        Some("text/plain; charset=utf8"),
+        mime::sniff("caf\u{e9}".as_bytes())
+    );
+    // a character cut off by the end of what was read
+    assert_eq!(Some("text/plain; charset=utf8"), mime::sniff(b"caf\xc3"));
+    assert_eq!(None, mime::sniff(b"\x01\x02\x03\xff"));
+    assert_eq!(None, mime::sniff(b""));
+}
+
+#[test]
+fn sniffs_extensionless_files() {
+    let path = env::temp_dir().join(format!("vial-sniff-{}", std::process::id()));
+    fs::write(&path, b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR").unwrap();
+    let path = path.to_str().unwrap();
+
+    assert_eq!(
+        "application/octet-stream",
+        Response::from_file(path).content_type()
+    );
+    mime::use_sniffing(true);
+    assert!(mime::uses_sniffing());
+    assert_eq!("image/png", Response::from_file(path).content_type());
+    mime::use_sniffing(false);
+
+    fs::remove_file(path).unwrap();
+}
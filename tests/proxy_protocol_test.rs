@@ -0,0 +1,108 @@
+mod common;
+
+use {
+    common::{connect, read_body, read_head, router, server},
+    std::io::Write,
+    vial::proxy,
+};
+
+mod app {
+    use vial::prelude::*;
+
+    routes! {
+        GET "/" => |req| format!("{} {}", req.remote_addr(), req.peer_addr());
+    }
+}
+
+/// Send `preamble` and a request, returning the response body, or
+/// `None` if the connection was dropped.
+fn send(addr: &str, preamble: &[u8]) -> Option<String> {
+    let mut client = connect(addr);
+    let mut raw = preamble.to_vec();
+    raw.extend_from_slice(b"GET / HTTP/1.1\r\nHost: localhost\r\n\r\n");
+    client.write_all(&raw).unwrap();
+
+    let head = read_head(&mut client);
+    if head.is_empty() {
+        return None;
+    }
+    Some(read_body(&mut client, &head))
+}
+
+/// A version 2 header with the given command, family, and address
+/// block.
+fn v2(command: u8, family: u8, addrs: &[u8]) -> Vec<u8> {
+    let mut out = b"\r\n\r\n\0\r\nQUIT\n".to_vec();
+    out.push(0x20 | command);
+    out.push(family);
+    out.extend_from_slice(&(addrs.len() as u16).to_be_bytes());
+    out.extend_from_slice(addrs);
+    out
+}
+
+#[test]
+fn version_1() {
+    proxy::use_proxy_protocol(true);
+    let addr = server("127.0.0.1:0", router(app::vial_add_to_router));
+    assert_eq!(
+        Some("192.0.2.1:56324 192.0.2.1:56324".to_string()),
+        send(&addr, b"PROXY TCP4 192.0.2.1 198.51.100.1 56324 443\r\n")
+    );
+    assert_eq!(
+        Some("[2001:db8::1]:4000 [2001:db8::1]:4000".to_string()),
+        send(&addr, b"PROXY TCP6 2001:db8::1 2001:db8::2 4000 443\r\n")
+    );
+
+    // no address, so the connection's own is used
+    let body = send(&addr, b"PROXY UNKNOWN\r\n").unwrap();
+    assert!(body.starts_with("127.0.0.1:"));
+}
+
+#[test]
+fn version_2() {
+    proxy::use_proxy_protocol(true);
+    let addr = server("127.0.0.1:0", router(app::vial_add_to_router));
+    let tcp4 = [192, 0, 2, 1, 198, 51, 100, 1, 0xdc, 0x04, 0x01, 0xbb];
+    assert_eq!(
+        Some("192.0.2.1:56324 192.0.2.1:56324".to_string()),
+        send(&addr, &v2(1, 0x11, &tcp4))
+    );
+
+    // TLVs after the addresses are skipped
+    let mut with_tlv = tcp4.to_vec();
+    with_tlv.extend_from_slice(&[0x04, 0x00, 0x01, 0x00]);
+    assert_eq!(
+        Some("192.0.2.1:56324 192.0.2.1:56324".to_string()),
+        send(&addr, &v2(1, 0x11, &with_tlv))
+    );
+
+    let mut tcp6 = vec![0x20, 0x01, 0x0d, 0xb8];
+    tcp6.extend_from_slice(&[0; 11]);
+    tcp6.push(1);
+    tcp6.extend_from_slice(&[0; 16]);
+    tcp6.extend_from_slice(&[0x0f, 0xa0, 0x01, 0xbb]);
+    assert_eq!(
+        Some("[2001:db8::1]:4000 [2001:db8::1]:4000".to_string()),
+        send(&addr, &v2(1, 0x21, &tcp6))
+    );
+
+    // LOCAL health checks
+    let body = send(&addr, &v2(0, 0x00, &[])).unwrap();
+    assert!(body.starts_with("127.0.0.1:"));
+}
+
+#[test]
+fn rejects_missing_or_bad_header() {
+    proxy::use_proxy_protocol(true);
+    let addr = server("127.0.0.1:0", router(app::vial_add_to_router));
+    assert_eq!(None, send(&addr, b""));
+    assert_eq!(
+        None,
+        send(&addr, b"PROXY TCP4 nope 198.51.100.1 56324 443\r\n")
+    );
+    assert_eq!(
+        None,
+        send(&addr, b"PROXY TCP6 192.0.2.1 198.51.100.1 56324 443\r\n")
+    );
+    assert_eq!(None, send(&addr, &v2(1, 0x11, &[192, 0, 2, 1])));
+}
@@ -0,0 +1,156 @@
+mod common;
+
+use {
+    common::{connect, read_body, read_head, router, server},
+    std::{io::Write, net::SocketAddr, sync::Once},
+    vial::{proxy, test::Client},
+};
+
+mod app {
+    use vial::prelude::*;
+
+    routes! {
+        GET "/" => |req| format!(
+            "{} {} {} {}",
+            req.remote_addr(),
+            req.peer_addr(),
+            req.scheme(),
+            req.host().unwrap_or_default()
+        );
+    }
+}
+
+fn client() -> Client {
+    static TRUST: Once = Once::new();
+    TRUST.call_once(|| {
+        proxy::trust("10.0.0.0/8").unwrap();
+        proxy::trust_with("fd00::1", proxy::Header::Forwarded).unwrap();
+    });
+    Client::new(router(app::vial_add_to_router))
+}
+
+fn addr(s: &str) -> SocketAddr {
+    s.parse().unwrap()
+}
+
+#[test]
+fn untrusted_peer() {
+    let client = client();
+    let res = client
+        .get("/")
+        .remote_addr(addr("203.0.113.9:5000"))
+        .header("Host", "app.local")
+        .header("X-Forwarded-For", "1.2.3.4")
+        .header("X-Forwarded-Proto", "https")
+        .header("X-Forwarded-Host", "evil.example")
+        .send();
+    assert_eq!(
+        "203.0.113.9:5000 203.0.113.9:5000 http app.local",
+        res.body()
+    );
+}
+
+#[test]
+fn x_forwarded_for() {
+    let client = client();
+    let res = client
+        .get("/")
+        .remote_addr(addr("10.0.0.2:5000"))
+        .header("Host", "10.0.0.2")
+        .header("X-Forwarded-For", "6.6.6.6, 1.2.3.4, 10.0.0.5")
+        .header("X-Forwarded-Proto", "HTTPS")
+        .header("X-Forwarded-Host", "example.com")
+        .send();
+    // the spoofed 6.6.6.6 is skipped: 1.2.3.4 talked to our proxy
+    assert_eq!("1.2.3.4:0 10.0.0.2:5000 https example.com", res.body());
+
+    // all trusted: the first one is the client
+    let res = client
+        .get("/")
+        .remote_addr(addr("10.0.0.2:5000"))
+        .header("X-Forwarded-For", "10.9.9.9")
+        .send();
+    assert!(res.body().starts_with("10.9.9.9:0 "));
+}
+
+#[test]
+fn forwarded() {
+    let client = client();
+    let res = client
+        .get("/")
+        .remote_addr(addr("[fd00::1]:5000"))
+        .header(
+            "Forwarded",
+            "for=6.6.6.6, for=\"[2001:db8::17]:4711\";proto=https;host=example.com",
+        )
+        .header("X-Forwarded-For", "7.7.7.7")
+        .send();
+    assert_eq!(
+        "[2001:db8::17]:4711 [fd00::1]:5000 https example.com",
+        res.body()
+    );
+
+    // obfuscated clients leave the proxy as the remote address
+    let res = client
+        .get("/")
+        .remote_addr(addr("[fd00::1]:5000"))
+        .header("Forwarded", "for=_hidden")
+        .send();
+    assert!(res.body().starts_with("[fd00::1]:5000 "));
+}
+
+#[test]
+fn only_the_proxys_header() {
+    let client = client();
+
+    // our proxy sets X-Forwarded-For and passes Forwarded along, so a
+    // client can't pick its own address with it
+    let res = client
+        .get("/")
+        .remote_addr(addr("10.0.0.2:5000"))
+        .header("Forwarded", "for=1.2.3.4;proto=https;host=evil.example")
+        .header("X-Forwarded-For", "5.6.7.8")
+        .send();
+    assert!(res.body().starts_with("5.6.7.8:0 10.0.0.2:5000 http"));
+    let res = client
+        .get("/")
+        .remote_addr(addr("10.0.0.2:5000"))
+        .header("Forwarded", "for=1.2.3.4")
+        .send();
+    assert!(res.body().starts_with("10.0.0.2:5000 "));
+
+    // and the other way around
+    let res = client
+        .get("/")
+        .remote_addr(addr("[fd00::1]:5000"))
+        .header("X-Forwarded-For", "1.2.3.4")
+        .send();
+    assert!(res.body().starts_with("[fd00::1]:5000 "));
+}
+
+#[test]
+fn networks() {
+    client();
+    assert!(proxy::trust("nope").is_err());
+    assert!(proxy::trust("10.0.0.0/33").is_err());
+    assert!(proxy::is_trusted("10.200.3.4".parse().unwrap()));
+    assert!(proxy::is_trusted("::ffff:10.1.1.1".parse().unwrap()));
+    assert!(proxy::is_trusted("fd00::1".parse().unwrap()));
+    assert!(!proxy::is_trusted("fd00::2".parse().unwrap()));
+    assert!(!proxy::is_trusted("11.0.0.1".parse().unwrap()));
+}
+
+#[test]
+fn server_sets_peer_addr() {
+    let addr = server("127.0.0.1:0", router(app::vial_add_to_router));
+    let mut client = connect(&addr);
+    let local = client.local_addr().unwrap();
+    client
+        .write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\nX-Forwarded-For: 1.2.3.4\r\n\r\n")
+        .unwrap();
+    let head = read_head(&mut client);
+    assert_eq!(
+        format!("{} {} http localhost", local, local),
+        read_body(&mut client, &head)
+    );
+}
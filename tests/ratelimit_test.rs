@@ -0,0 +1,142 @@
+use {
+    std::{thread, time::Duration},
+    vial::{
+        ratelimit::{self, Algorithm, RateLimit},
+        test::Client,
+        Request, Router,
+    },
+};
+
+mod app {
+    use vial::{
+        prelude::*,
+        ratelimit::{self, limit},
+    };
+
+    fn by_header(req: &mut Request) -> Option<Response> {
+        ratelimit::check("by_header", req)
+    }
+
+    fn by_key(req: &mut Request) -> Option<Response> {
+        ratelimit::check("by_key", req)
+    }
+
+    routes! {
+        GET "/" => |_| "Home";
+
+        #[filter(limit)]
+        POST "/login" => |_| "Welcome";
+
+        #[filter(by_header)]
+        GET "/api" => |_| "API";
+
+        #[filter(by_key)]
+        GET "/user" => |_| "User";
+    }
+}
+
+fn client() -> Client {
+    let mut router = Router::new();
+    app::vial_add_to_router(&mut router);
+    Client::new(router)
+}
+
+#[test]
+fn limit_filter() {
+    ratelimit::set(RateLimit::new(3, Duration::from_secs(60)));
+    let client = client();
+    for _ in 0..3 {
+        assert_eq!(200, client.post("/login").send().code());
+    }
+
+    let res = client.post("/login").send();
+    assert_eq!(429, res.code());
+    assert_eq!("429 Too Many Requests", res.body());
+    // one token comes back every 20 seconds
+    assert_eq!(Some("20"), res.header("Retry-After"));
+    assert_eq!(Some("3"), res.header("RateLimit-Limit"));
+    assert_eq!(Some("0"), res.header("RateLimit-Remaining"));
+    assert_eq!(Some("20"), res.header("RateLimit-Reset"));
+
+    // other routes aren't limited
+    assert_eq!(200, client.get("/").send().code());
+}
+
+#[test]
+fn header_key() {
+    ratelimit::add(
+        "by_header",
+        RateLimit::new(1, Duration::from_secs(60)).by_header("X-Api-Key"),
+    );
+    let client = client();
+    let get = |key: &str| client.get("/api").header("X-Api-Key", key).send().code();
+    assert_eq!(200, get("one"));
+    assert_eq!(429, get("one"));
+    assert_eq!(200, get("two"));
+    assert_eq!(429, get("two"));
+
+    // no header means counted by IP
+    assert_eq!(200, client.get("/api").send().code());
+    assert_eq!(429, client.get("/api").send().code());
+}
+
+#[test]
+fn custom_key() {
+    ratelimit::add(
+        "by_key",
+        RateLimit::new(2, Duration::from_secs(60))
+            .by_key(|req: &Request| req.query("user").map(|u| u.to_string())),
+    );
+    let client = client();
+    let get = |user: &str| client.get("/user").query("user", user).send().code();
+    assert_eq!(200, get("alice"));
+    assert_eq!(200, get("alice"));
+    assert_eq!(429, get("alice"));
+    assert_eq!(200, get("bob"));
+}
+
+#[test]
+fn token_bucket_refills() {
+    let limit = RateLimit::new(2, Duration::from_millis(100));
+    let req = Request::default();
+    assert!(limit.check(&req).is_none());
+    assert!(limit.check(&req).is_none());
+    assert!(limit.check(&req).is_some());
+
+    thread::sleep(Duration::from_millis(60));
+    assert!(limit.check(&req).is_none());
+    assert!(limit.check(&req).is_some());
+}
+
+#[test]
+fn sliding_window() {
+    let limit = RateLimit::new(2, Duration::from_millis(200)).algorithm(Algorithm::SlidingWindow);
+    let req = Request::default();
+    assert!(limit.check(&req).is_none());
+    assert!(limit.check(&req).is_none());
+    let res = limit.check(&req).unwrap();
+    assert_eq!(Some("1"), res.header("Retry-After"));
+
+    // just into the next window, the last one still counts in full
+    thread::sleep(Duration::from_millis(210));
+    assert!(limit.check(&req).is_some());
+
+    // two windows later, it's forgotten
+    thread::sleep(Duration::from_millis(400));
+    assert!(limit.check(&req).is_none());
+}
+
+#[test]
+fn forgets_quiet_clients() {
+    let limit = RateLimit::new(5, Duration::from_millis(50)).by_header("X-Id");
+    for id in &["a", "b", "c"] {
+        let req = Request::default().with_header("X-Id", id);
+        assert!(limit.check(&req).is_none());
+    }
+    assert_eq!(3, limit.len());
+
+    thread::sleep(Duration::from_millis(120));
+    let req = Request::default().with_header("X-Id", "d");
+    assert!(limit.check(&req).is_none());
+    assert_eq!(1, limit.len());
+}
@@ -23,16 +23,160 @@ fn basic_cookies() {
     let req = Request::from_reader(File::open("tests/http/cookies_GET.txt").unwrap()).unwrap();
     assert_eq!("GET", req.method());
     assert_eq!("/counter.php", req.path());
-    assert_eq!("50", req.cookie("Count").unwrap());
-    assert_eq!("Ricardo", req.cookie("NAME").unwrap());
+    assert_eq!("50", req.cookie("count").unwrap());
+    assert_eq!("Ricardo", req.cookie("name").unwrap());
+    assert_eq!(None, req.cookie("Count"));
+    assert_eq!(None, req.cookie("NAME"));
+}
+
+#[test]
+#[cfg(feature = "cookies")]
+fn malformed_cookies() {
+    let req =
+        Request::from_reader(File::open("tests/http/cookies_malformed_GET.txt").unwrap()).unwrap();
+    assert_eq!("abc", req.cookie("SessionID").unwrap());
+    assert_eq!(vec!["abc", "old"], req.cookie_all("SessionID"));
+    assert_eq!("dark", req.cookie("Theme").unwrap());
+    assert_eq!("%zz", req.cookie("broken").unwrap());
+    assert_eq!("item 1", req.cookie("cart").unwrap());
+    assert_eq!(None, req.cookie("garbage"));
+    assert_eq!(
+        vec!["SessionID", "Theme", "broken", "cart", "SessionID"],
+        req.cookies().map(|(k, _)| k).collect::<Vec<_>>()
+    );
 }
 
 #[test]
 #[cfg(feature = "sessions")]
 fn basic_sessions() {
+    vial::session::set_secret("vial-test-secret");
     let req = Request::from_reader(File::open("tests/http/session_GET.txt").unwrap()).unwrap();
     assert_eq!("GET", req.method());
     assert_eq!("/counter.php", req.path());
     assert_eq!("100", req.session("Count").unwrap());
     assert_eq!("Roger", req.session("NAME").unwrap());
 }
+
+#[test]
+fn setters() {
+    let req = Request::from_path("/search")
+        .with_method("POST")
+        .with_header("Content-Type", "text/plain")
+        .with_header("content-type", "application/json")
+        .with_query("q", "cats & dogs")
+        .with_query("page", "2")
+        .with_form("name", "Alice")
+        .with_remote_addr("10.0.0.1:1234".parse().unwrap());
+    assert_eq!("POST", req.method());
+    assert_eq!("application/json", req.header("Content-Type").unwrap());
+    assert_eq!(1, req.headers().len());
+    assert_eq!("/search", req.path());
+    assert_eq!("/search?q=cats%20%26%20dogs&page=2", req.full_path());
+    assert_eq!(Some("2"), req.query("page"));
+    assert_eq!(Some("Alice"), req.form("name"));
+    assert_eq!("10.0.0.1:1234", req.remote_addr().to_string());
+}
+
+#[test]
+fn builder() {
+    let builder = Request::builder()
+        .method("PUT")
+        .path("/users/1")
+        .query("draft", "yes")
+        .header("Authorization", "Basic YWxpY2U6c2VjcmV0")
+        .form("name", "Alice Smith");
+    assert!(String::from_utf8(builder.to_bytes())
+        .unwrap()
+        .starts_with("PUT /users/1?draft=yes HTTP/1.1\r\n"));
+
+    let req = builder.build().unwrap();
+    assert_eq!("PUT", req.method());
+    assert_eq!("/users/1", req.path());
+    assert_eq!(Some("yes"), req.query("draft"));
+    assert_eq!(
+        "Basic YWxpY2U6c2VjcmV0",
+        req.header("authorization").unwrap()
+    );
+    assert_eq!(
+        "application/x-www-form-urlencoded",
+        req.header("Content-Type").unwrap()
+    );
+    assert_eq!(Some("Alice Smith"), req.form("name"));
+    assert_eq!("name=Alice%20Smith", req.body());
+
+    let req = Request::builder().path("/has space").build();
+    assert!(req.is_err());
+}
+
+#[test]
+#[cfg(feature = "cookies")]
+fn builder_cookies() {
+    let req = Request::builder()
+        .cookie("Count", "50")
+        .cookie("name", "Ricardo")
+        .build()
+        .unwrap();
+    assert_eq!("50", req.cookie("Count").unwrap());
+    assert_eq!("Ricardo", req.cookie("name").unwrap());
+
+    let req = req.with_cookie("Count", "51");
+    assert_eq!("51", req.cookie("Count").unwrap());
+}
+
+#[test]
+#[cfg(feature = "cookies")]
+fn cookie_names_are_case_sensitive() {
+    let raw = "GET / HTTP/1.1\r\nCookie: SID=upper; sid=lower; SID=again\r\n\r\n";
+    let req = Request::from_reader(raw.as_bytes()).unwrap();
+    assert_eq!(Some("upper"), req.cookie("SID"));
+    assert_eq!(Some("lower"), req.cookie("sid"));
+    assert_eq!(None, req.cookie("Sid"));
+    assert_eq!(vec!["upper", "again"], req.cookie_all("SID"));
+    assert_eq!(vec!["lower"], req.cookie_all("sid"));
+
+    // replacing one leaves the other alone
+    let req = req.with_cookie("sid", "new");
+    assert_eq!(Some("new"), req.cookie("sid"));
+    assert_eq!(vec!["upper", "again"], req.cookie_all("SID"));
+}
+
+#[test]
+fn header_access() {
+    let raw = "POST /upload HTTP/1.1\r\nHost: example.com\r\nvary: a\r\nVARY: b\r\n\
+               Content-Type: multipart/form-data; charset=UTF-8; boundary=\"xyz\"\r\n\
+               Content-Length: 0\r\nAuthorization: Basic YWxpY2U6czNjcmV0\r\n\
+               User-Agent: curl/8.0\r\n\r\n";
+    let req = Request::from_reader(raw.as_bytes()).unwrap();
+
+    assert_eq!(7, req.headers().len());
+    assert_eq!(Some(("vary", "a")), req.headers().nth(1));
+    assert_eq!(vec!["a", "b"], req.header_all("Vary"));
+    assert_eq!("a, b", req.header("vary").unwrap());
+    assert!(req.header_all("X-Missing").is_empty());
+
+    let ct = req.content_type().unwrap();
+    assert_eq!("multipart/form-data", ct.mime());
+    assert!(ct.is("Multipart/Form-Data"));
+    assert_eq!(Some("UTF-8"), ct.charset());
+    assert_eq!(Some("xyz"), ct.param("Boundary"));
+    assert_eq!(None, ct.param("missing"));
+
+    let auth = req.authorization().unwrap();
+    assert_eq!("Basic", auth.scheme());
+    assert_eq!(None, auth.bearer());
+    assert_eq!(
+        Some(("alice".to_string(), "s3cret".to_string())),
+        auth.basic()
+    );
+
+    assert_eq!(Some(0), req.content_length());
+    assert_eq!("example.com", req.host().unwrap());
+    assert_eq!(Some("curl/8.0"), req.user_agent());
+
+    let req = req
+        .with_header("vary", "c")
+        .with_header("Authorization", "Bearer abc123");
+    assert_eq!(vec!["c"], req.header_all("VARY"));
+    assert_eq!(Some("abc123"), req.authorization().unwrap().bearer());
+    assert_eq!(None, req.authorization().unwrap().basic());
+}
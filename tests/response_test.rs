@@ -13,6 +13,67 @@ fn set_header() {
     );
 }
 
+#[test]
+fn multiple_headers() {
+    let mut res = Response::new()
+        .with_header("Link", "</a.css>; rel=preload")
+        .with_appended_header("link", "</b.js>; rel=preload")
+        .with_header("X-Later", "1");
+    assert_eq!(Some("</a.css>; rel=preload"), res.header("LINK"));
+    assert_eq!(
+        vec!["</a.css>; rel=preload", "</b.js>; rel=preload"],
+        res.header_all("Link")
+    );
+
+    res.set_header("location", "/one");
+    res.set_header("Location", "/two");
+    assert_eq!(vec!["/two"], res.header_all("LOCATION"));
+
+    res.remove_header("LINK");
+    assert!(res.header_all("Link").is_empty());
+    let names = res.headers().iter().map(|(n, _)| n).collect::<Vec<_>>();
+    assert_eq!(
+        vec!["Content-Type", "Content-Length", "X-Later", "location"],
+        names
+    );
+
+    let mut out = vec![];
+    Response::new()
+        .with_header("Vary", "Accept")
+        .with_appended_header("Vary", "Cookie")
+        .write(&mut out)
+        .unwrap();
+    let out = String::from_utf8_lossy(&out);
+    assert!(
+        out.contains("\r\nVary: Accept\r\nVary: Cookie\r\n"),
+        "{}",
+        out
+    );
+}
+
+#[test]
+fn header_injection() {
+    let res = Response::new()
+        .with_header("X-Name", "bob\r\nSet-Cookie: admin=1")
+        .with_appended_header("X-Name", "a\nb")
+        .with_header("Bad Name", "value")
+        .with_header("X-Tab", "a\tb");
+    assert_eq!(None, res.header("X-Name"));
+    assert_eq!(None, res.header("Bad Name"));
+    assert_eq!(Some("a\tb"), res.header("X-Tab"));
+
+    let mut headers = vial::HeaderMap::new();
+    assert_eq!(
+        Err(vial::Error::InvalidHeaderValue),
+        headers.set("X-Name", "bob\r\n")
+    );
+    assert_eq!(
+        Err(vial::Error::InvalidHeaderName),
+        headers.append("X:Name", "bob")
+    );
+    assert!(headers.is_empty());
+}
+
 #[test]
 fn from_header() {
     let v = "12345";
@@ -69,8 +130,8 @@ fn from_reader() {
         &version,
         &date,
         "Connection: close",
-        "content-length: 0",
-        "content-type: text/html; charset=utf8",
+        "Content-Length: 0",
+        "Content-Type: text/html; charset=utf8",
     ];
 
     res1.write(&mut out).unwrap();
@@ -183,7 +244,16 @@ fn redirect_to() {
 
 #[test]
 fn write_response() {
-    assert!(true); // TODO
+    let mut out = vec![];
+    Response::from("Hi there").write(&mut out).unwrap();
+    let out = String::from_utf8_lossy(&out);
+    assert!(out.starts_with("HTTP/1.1 200 OK\r\n"));
+    assert!(out.contains("\r\nContent-Length: 8\r\n"));
+    assert!(out.ends_with("\r\n\r\nHi there"));
+
+    let mut out = vec![];
+    Response::from(404).write(&mut out).unwrap();
+    assert!(out.starts_with(b"HTTP/1.1 404 Not Found\r\n"));
 }
 
 #[test]
@@ -220,17 +290,73 @@ fn test_cookies() {
     let mut res = Response::new();
     res.set_cookie("Count", "2");
     assert_eq!("2", res.cookie("Count").unwrap());
+    assert_eq!(None, res.cookie("count"));
     let mut out = vec![];
     res.write(&mut out).unwrap();
     let out = String::from_utf8_lossy(&out);
-    assert!(out.contains("\r\nSet-Cookie: count=2\r\n"));
+    assert!(out.contains("\r\nSet-Cookie: Count=2\r\n"));
 
     let mut res = Response::new();
     res.remove_cookie("Count");
     let mut out = vec![];
     res.write(&mut out).unwrap();
     let out = String::from_utf8_lossy(&out);
-    assert!(out.contains("\r\nSet-Cookie: count=; Expires=Thu, 01 Jan 1970 00:00:00 GMT\r\n"));
+    assert!(out.contains("\r\nSet-Cookie: Count=; Expires=Thu, 01 Jan 1970 00:00:00 GMT\r\n"));
+}
+
+#[test]
+#[cfg(feature = "cookies")]
+fn invalid_cookie_names() {
+    let res = Response::new()
+        .with_cookie("id\r\nLocation: /evil", "1")
+        .with_cookie("a b", "2")
+        .with_cookie("", "3")
+        .without_cookie("x=y")
+        .with_cookie("SessionID", "4");
+    assert_eq!(None, res.cookie("id\r\nLocation: /evil"));
+    assert_eq!(None, res.cookie("a b"));
+    assert_eq!(Some("4"), res.cookie("SessionID"));
+
+    let mut out = vec![];
+    res.write(&mut out).unwrap();
+    let out = String::from_utf8_lossy(&out);
+    assert!(!out.contains("Location"), "{}", out);
+    assert_eq!(1, out.matches("Set-Cookie:").count(), "{}", out);
+    assert!(out.contains("\r\nSet-Cookie: SessionID=4\r\n"));
+}
+
+#[test]
+#[cfg(feature = "cookies")]
+fn test_cookie_attributes() {
+    use {
+        std::time::Duration,
+        vial::{Cookie, SameSite},
+    };
+    let res = Response::new()
+        .with_cookie("greeting", "hi there; friend")
+        .with_cookie(
+            "token",
+            Cookie::new("abc")
+                .path("/admin")
+                .domain("example.com")
+                .max_age(Duration::from_secs(3600))
+                .secure(true)
+                .http_only(true)
+                .same_site(SameSite::Strict),
+        );
+    assert_eq!("hi there; friend", res.cookie("greeting").unwrap());
+    assert_eq!("abc", res.cookie("token").unwrap());
+
+    let mut out = vec![];
+    res.write(&mut out).unwrap();
+    let out = String::from_utf8_lossy(&out);
+    assert!(out.contains("\r\nSet-Cookie: greeting=hi%20there%3B%20friend\r\n"));
+    assert!(out.contains(
+        "\r\nSet-Cookie: token=abc; Max-Age=3600; Domain=example.com; Path=/admin; Secure; HttpOnly; SameSite=Strict\r\n"
+    ));
+
+    let cookie = Cookie::new("x").path("/; Secure\r\nX-Evil: 1");
+    assert_eq!("a=x; Path=/ SecureX-Evil: 1", cookie.to_header("a"));
 }
 
 #[test]
@@ -238,19 +364,31 @@ fn test_cookies() {
 fn test_sessions() {
     let mut res = Response::new();
     res.set_session("Count", "2");
+    res.set_session("Name", "Roger");
     assert_eq!("2", res.session("Count").unwrap());
     let mut out = vec![];
     res.write(&mut out).unwrap();
     let out = String::from_utf8_lossy(&out);
-    assert!(out.contains("\r\nSet-Cookie: __vial_count="));
+    assert_eq!(1, out.matches("Set-Cookie: ").count());
+    assert!(out.contains("\r\nSet-Cookie: __vial_session="));
+    assert!(out.contains("; Path=/; HttpOnly; SameSite=Lax\r\n"));
 
     let mut res = Response::new();
     res.remove_session("Count");
-    assert_eq!("", res.session("Count").unwrap());
+    assert_eq!(None, res.session("Count"));
     let mut out = vec![];
     res.write(&mut out).unwrap();
     let out = String::from_utf8_lossy(&out);
-    assert!(
-        out.contains("\r\nSet-Cookie: __vial_count=; Expires=Thu, 01 Jan 1970 00:00:00 GMT\r\n")
-    );
+    assert!(out.contains(
+        "\r\nSet-Cookie: __vial_session=; Expires=Thu, 01 Jan 1970 00:00:00 GMT; Path=/; HttpOnly; SameSite=Lax\r\n"
+    ));
+
+    let mut out = vec![];
+    Response::new().write(&mut out).unwrap();
+    assert!(!String::from_utf8_lossy(&out).contains("Set-Cookie"));
+
+    let big = "x".repeat(5000);
+    let mut out = vec![];
+    Response::from_session("big", &big).write(&mut out).unwrap();
+    assert!(!String::from_utf8_lossy(&out).contains("Set-Cookie"));
 }
@@ -0,0 +1,123 @@
+#![cfg(unix)]
+
+mod common;
+
+use {
+    common::{connect, read_body, read_head, router, try_connect},
+    std::{
+        env,
+        io::Write,
+        net::{TcpListener, TcpStream},
+        process::{Command, Stdio},
+        thread,
+        time::{Duration, Instant},
+    },
+};
+
+extern "C" {
+    fn kill(pid: i32, signal: i32) -> i32;
+}
+
+const SIGHUP: i32 = 1;
+const SIGKILL: i32 = 9;
+
+mod app {
+    use {
+        std::{thread, time::Duration},
+        vial::prelude::*,
+    };
+
+    routes! {
+        GET "/" => |_| std::process::id().to_string();
+        GET "/slow" => |_| {
+            thread::sleep(Duration::from_secs(1));
+            std::process::id().to_string()
+        };
+    }
+}
+
+/// Send a request, returning the status code and body.
+fn get(mut client: TcpStream, path: &str) -> (String, String) {
+    write!(client, "GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", path).unwrap();
+    let head = read_head(&mut client);
+    let body = read_body(&mut client, &head);
+    (head[9..12].to_string(), body)
+}
+
+/// Runs in a child process started by `restarts_without_dropping`,
+/// and again in the process it restarts into.
+#[test]
+fn restart_child() {
+    let addr = match env::var("VIAL_RESTART_ADDR") {
+        Ok(addr) => addr,
+        Err(_) => return,
+    };
+    vial::restart::enable();
+    assert!(vial::restart::is_enabled());
+    vial::restart::set_drain_timeout(Duration::from_secs(5));
+
+    vial::run(addr.as_str(), router(app::vial_add_to_router), Some("")).unwrap()
+}
+
+#[test]
+fn restarts_without_dropping() {
+    if env::var("VIAL_RESTART_ADDR").is_ok() {
+        return;
+    }
+
+    let addr = TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .to_string();
+    let mut child = Command::new(env::current_exe().unwrap())
+        .args(["--exact", "restart_child", "--nocapture"])
+        .env("VIAL_RESTART_ADDR", &addr)
+        .stdout(Stdio::null())
+        .spawn()
+        .unwrap();
+    let old = child.id().to_string();
+
+    assert_eq!(("200".to_string(), old.clone()), get(connect(&addr), "/"));
+
+    // in progress when the restart happens
+    let slow = {
+        let addr = addr.clone();
+        thread::spawn(move || get(connect(&addr), "/slow"))
+    };
+    thread::sleep(Duration::from_millis(200));
+    unsafe { kill(child.id() as i32, SIGHUP) };
+
+    // every request is answered, by one process or the other
+    let started = Instant::now();
+    let new = loop {
+        let client = try_connect(&addr).expect("connection refused during restart");
+        let (code, pid) = get(client, "/");
+        assert_eq!("200", code);
+        if pid != old {
+            break pid;
+        }
+        assert!(started.elapsed() < Duration::from_secs(20));
+        thread::sleep(Duration::from_millis(20));
+    };
+
+    assert_eq!(("200".to_string(), old), slow.join().unwrap());
+
+    // the old process finishes up and exits
+    let started = Instant::now();
+    let status = loop {
+        if let Some(status) = child.try_wait().unwrap() {
+            break status;
+        }
+        if started.elapsed() > Duration::from_secs(10) {
+            child.kill().unwrap();
+            panic!("old process didn't exit");
+        }
+        thread::sleep(Duration::from_millis(50));
+    };
+    assert!(status.success());
+
+    let client = try_connect(&addr).unwrap();
+    assert_eq!(("200".to_string(), new.clone()), get(client, "/"));
+    unsafe { kill(new.parse().unwrap(), SIGKILL) };
+}
@@ -1,17 +1,234 @@
 #[test]
 #[cfg(feature = "sessions")]
-fn session_encode() {
+fn session_encode_decode() {
     use vial::session::Session;
     let session = Session::new("secret-key");
     let encoded = session.encode("hello");
-    assert_eq!(encoded, "mYcOuCZs");
+    assert_ne!(encoded, "hello");
+    assert!(!encoded.contains("hello"));
+    assert_eq!(session.decode(&encoded), Ok("hello".to_string()));
 }
 
 #[test]
 #[cfg(feature = "sessions")]
-fn session_decode() {
-    use vial::session::Session;
+fn session_rejects_tampering() {
+    use vial::{session::Session, Error};
     let session = Session::new("secret-key");
-    let decoded = session.decode("mYcOuCZs");
-    assert_eq!(decoded, Ok("hello".to_string()));
+    let encoded = session.encode("admin=false");
+
+    let (data, tag) = encoded.split_once('.').unwrap();
+    let mut data = data.to_string().into_bytes();
+    data[0] = if data[0] == b'A' { b'B' } else { b'A' };
+    let tampered = format!("{}.{}", String::from_utf8(data).unwrap(), tag);
+    assert_eq!(session.decode(&tampered), Err(Error::SessionDecode));
+
+    assert_eq!(session.decode("mYcOuCZs"), Err(Error::SessionDecode));
+    let other = Session::new("other-key");
+    assert_eq!(other.decode(&encoded), Err(Error::SessionDecode));
+}
+
+#[test]
+#[cfg(feature = "sessions")]
+fn session_key_rotation() {
+    use vial::session::Session;
+    let old = Session::new("old-key");
+    let rotated = Session::with_secrets(&["new-key", "old-key"], None);
+    let new = Session::new("new-key");
+
+    let encoded = old.encode("hello");
+    assert_eq!(rotated.decode(&encoded), Ok("hello".to_string()));
+
+    let encoded = rotated.encode("hello");
+    assert_eq!(new.decode(&encoded), Ok("hello".to_string()));
+    assert!(old.decode(&encoded).is_err());
+}
+
+#[test]
+#[cfg(feature = "sessions")]
+fn session_expiry() {
+    use {
+        std::{thread, time::Duration},
+        vial::{session::Session, Error},
+    };
+    let session = Session::with_secrets(&["secret-key"], Some(Duration::from_secs(0)));
+    let encoded = session.encode("hello");
+    thread::sleep(Duration::from_millis(1100));
+    assert_eq!(session.decode(&encoded), Err(Error::SessionExpired));
+
+    let session = Session::with_secrets(&["secret-key"], Some(Duration::from_secs(60)));
+    let encoded = session.encode("hello");
+    assert_eq!(session.decode(&encoded), Ok("hello".to_string()));
+}
+
+#[test]
+#[cfg(feature = "sessions")]
+fn session_cookie() {
+    use vial::{session::Session, Error};
+    let mut session = Session::new("secret-key");
+    session.set("Name", "Alice & Bob");
+    session.set("count", "1=2");
+    let cookie = session.to_cookie().unwrap();
+
+    let mut loaded = Session::new("secret-key");
+    loaded.load_cookie(&cookie).unwrap();
+    assert_eq!(Some("Alice & Bob"), loaded.get("name"));
+    assert_eq!(Some("1=2"), loaded.get("count"));
+    assert!(!loaded.is_dirty());
+
+    let mut other = Session::new("other-key");
+    assert_eq!(Err(Error::SessionDecode), other.load_cookie(&cookie));
+    assert!(other.is_empty());
+
+    session.set("big", &"x".repeat(4096));
+    assert!(matches!(
+        session.to_cookie(),
+        Err(Error::SessionTooLarge(..))
+    ));
+}
+
+#[test]
+#[cfg(feature = "sessions")]
+fn session_merge() {
+    use vial::session::Session;
+    let mut base = Session::new("secret-key");
+    base.set("name", "Alice");
+    base.set("count", "1");
+    base.set("theme", "dark");
+
+    let mut changes = Session::new("secret-key");
+    changes.set("count", "2");
+    changes.remove("theme");
+    changes.merge(&base);
+    assert_eq!(Some("Alice"), changes.get("name"));
+    assert_eq!(Some("2"), changes.get("count"));
+    assert_eq!(None, changes.get("theme"));
+
+    let mut changes = Session::new("secret-key");
+    changes.clear();
+    changes.set("count", "3");
+    changes.merge(&base);
+    assert_eq!(None, changes.get("name"));
+    assert_eq!(Some("3"), changes.get("count"));
+    assert!(changes.is_dirty());
+
+    let mut changes = Session::new("secret-key");
+    assert!(!changes.is_dirty());
+    changes.regenerate();
+    assert!(changes.is_dirty());
+}
+
+#[test]
+#[cfg(feature = "sessions")]
+fn session_memory_store() {
+    use {
+        std::sync::Arc,
+        vial::session::{MemoryStore, Session},
+    };
+    let store = Arc::new(MemoryStore::new());
+    let mut session = Session::new("secret-key").with_store(store.clone());
+    session.set("name", "Alice");
+    session.set("bio", &"x".repeat(8192));
+    let cookie = session.save().unwrap().unwrap();
+    assert!(cookie.len() < 200);
+    assert!(!cookie.contains("Alice"));
+    assert_eq!(1, store.len());
+    let id = session.id().unwrap().to_string();
+
+    let mut loaded = Session::new("secret-key").with_store(store.clone());
+    loaded.load_cookie(&cookie).unwrap();
+    assert_eq!(Some("Alice"), loaded.get("name"));
+    assert_eq!(Some(id.as_str()), loaded.id());
+
+    // same ID is reused until the session is regenerated
+    loaded.set("name", "Bob");
+    assert_eq!(cookie, loaded.save().unwrap().unwrap());
+    loaded.regenerate();
+    let new_cookie = loaded.save().unwrap().unwrap();
+    assert_ne!(cookie, new_cookie);
+    assert_eq!(1, store.len());
+
+    // the old ID no longer works
+    let mut stale = Session::new("secret-key").with_store(store.clone());
+    assert!(stale.load_cookie(&cookie).is_err());
+    stale.load_cookie(&new_cookie).unwrap();
+    assert_eq!(Some("Bob"), stale.get("name"));
+
+    // clearing a session removes it from the store
+    stale.clear();
+    assert_eq!(None, stale.save().unwrap());
+    assert!(store.is_empty());
+    let mut gone = Session::new("secret-key").with_store(store);
+    assert!(gone.load_cookie(&new_cookie).is_err());
+}
+
+#[test]
+#[cfg(feature = "sessions")]
+fn session_store_expiry() {
+    use {
+        std::{sync::Arc, thread, time::Duration},
+        vial::session::{MemoryStore, Session, SessionStore},
+    };
+    let store = Arc::new(MemoryStore::new());
+    let mut session = Session::with_secrets(&["secret-key"], Some(Duration::from_millis(50)))
+        .with_store(store.clone());
+    session.set("name", "Alice");
+    session.save().unwrap();
+    let id = session.id().unwrap().to_string();
+    assert!(store.load(&id).is_some());
+
+    thread::sleep(Duration::from_millis(100));
+    assert!(store.load(&id).is_none());
+    assert_eq!(1, store.len());
+    store.sweep();
+    assert!(store.is_empty());
+}
+
+#[test]
+#[cfg(feature = "sessions")]
+fn session_file_store() {
+    use {
+        std::{collections::HashMap, sync::Arc, time::Duration},
+        vial::session::{FileStore, Session, SessionStore},
+    };
+    let dir = std::env::temp_dir().join(format!("vial-sessions-{}", std::process::id()));
+    let store = Arc::new(FileStore::new(&dir).unwrap());
+
+    let mut session = Session::new("secret-key").with_store(store.clone());
+    session.set("name", "Alice & Bob");
+    let cookie = session.save().unwrap().unwrap();
+    let id = session.id().unwrap().to_string();
+    assert!(dir.join(&id).exists());
+
+    let mut loaded = Session::new("secret-key").with_store(store.clone());
+    loaded.load_cookie(&cookie).unwrap();
+    assert_eq!(Some("Alice & Bob"), loaded.get("name"));
+
+    // IDs can't be used to reach outside the directory
+    assert!(store.load("../etc/passwd").is_none());
+    let data = HashMap::new();
+    assert!(store.save("../escape", &data, None).is_err());
+
+    // expired files are thrown away
+    store
+        .save("old", &data, Some(Duration::from_secs(0)))
+        .unwrap();
+    assert!(store.load("old").is_none());
+    assert!(!dir.join("old").exists());
+
+    // sweeping only touches expired sessions
+    store
+        .save("stale", &data, Some(Duration::from_secs(0)))
+        .unwrap();
+    std::fs::write(dir.join("notes.txt"), "not a session").unwrap();
+    std::fs::write(dir.join("README"), "not a session either").unwrap();
+    store.sweep().unwrap();
+    assert!(!dir.join("stale").exists());
+    assert!(dir.join("notes.txt").exists());
+    assert!(dir.join("README").exists());
+    assert!(dir.join(&id).exists());
+
+    loaded.clear();
+    assert_eq!(None, loaded.save().unwrap());
+    assert!(!dir.join(&id).exists());
+    std::fs::remove_dir_all(dir).unwrap();
 }
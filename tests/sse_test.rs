@@ -0,0 +1,110 @@
+mod common;
+
+use {
+    common::{connect, read_head, router, server},
+    std::{
+        io::{Read, Write},
+        thread,
+        time::Duration,
+    },
+    vial::{
+        sse::{Event, EventSender},
+        test::Client,
+        Error,
+    },
+};
+
+mod app {
+    use vial::{prelude::*, sse::Event};
+
+    routes! {
+        GET "/events" => |req| {
+            let start = req
+                .header("Last-Event-ID")
+                .and_then(|id| id.parse::<usize>().ok())
+                .unwrap_or(0);
+            Response::sse(move |mut events| {
+                for i in start + 1..=start + 3 {
+                    events
+                        .send(Event::new(format!("tick {}", i)).id(i.to_string()).event("tick"))
+                        .unwrap();
+                }
+            })
+        };
+    }
+}
+
+#[test]
+fn event_format() {
+    assert_eq!("data: hello\n\n", Event::new("hello").to_frame());
+    assert_eq!(
+        "id: 7\nevent: update\nretry: 3000\ndata: line one\ndata: line two\n\n",
+        Event::new("line one\r\nline two")
+            .id("7")
+            .event("up\ndate")
+            .retry(Duration::from_secs(3))
+            .to_frame()
+    );
+
+    // a bare \r ends a line too, so it can't sneak in other fields
+    assert_eq!(
+        "data: x\ndata: id: evil\ndata: \ndata: event: admin\n\n",
+        Event::new("x\rid: evil\n\revent: admin").to_frame()
+    );
+}
+
+#[test]
+fn test_client() {
+    let client = Client::new(router(app::vial_add_to_router));
+    let res = client.get("/events").send();
+    assert_eq!(200, res.code());
+    assert_eq!("text/event-stream", res.content_type());
+    assert_eq!(Some("no-cache"), res.header("Cache-Control"));
+    assert!(res
+        .body()
+        .starts_with("id: 1\nevent: tick\ndata: tick 1\n\nid: 2\n"));
+
+    let res = client.get("/events").header("Last-Event-ID", "2").send();
+    assert!(res.body().starts_with("id: 3\n"));
+}
+
+#[test]
+fn disconnect() {
+    struct Broken;
+    impl Write for Broken {
+        fn write(&mut self, _: &[u8]) -> std::io::Result<usize> {
+            Err(std::io::ErrorKind::BrokenPipe.into())
+        }
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let mut events = EventSender::new(Broken, None);
+    assert!(events.is_connected());
+    assert_eq!(Err(Error::ConnectionClosed), events.send("hello"));
+    assert!(!events.is_connected());
+
+    // heartbeats notice too
+    let events = EventSender::new(Broken, Some(Duration::from_millis(10)));
+    thread::sleep(Duration::from_millis(100));
+    assert!(!events.is_connected());
+}
+
+#[test]
+fn server_stream() {
+    let addr = server("127.0.0.1:0", router(app::vial_add_to_router));
+    let mut client = connect(&addr);
+    client
+        .write_all(b"GET /events HTTP/1.1\r\nHost: localhost\r\n\r\n")
+        .unwrap();
+    let head = read_head(&mut client);
+    assert!(head.starts_with("HTTP/1.1 200"));
+    assert!(head.contains("\r\nContent-Type: text/event-stream\r\n"));
+    assert!(!head.contains("Content-Length"));
+
+    // no length, so the stream runs until the server closes it
+    let mut body = String::new();
+    client.read_to_string(&mut body).unwrap();
+    assert_eq!("id: 1\nevent: tick\ndata: tick 1\n\nid: 2\nevent: tick\ndata: tick 2\n\nid: 3\nevent: tick\ndata: tick 3\n\n", body);
+}
@@ -0,0 +1,80 @@
+mod common;
+
+use {
+    common::{router, send, server},
+    vial::{
+        limits::{self, Limits},
+        Request,
+    },
+};
+
+mod app {
+    use vial::prelude::*;
+
+    routes! {
+        POST "/" => |req| format!("got {}", req.body());
+    }
+}
+
+fn strict_limits() {
+    limits::set(Limits::new().strict_parsing(true).max_headers(5));
+}
+
+#[test]
+fn accepts_good_requests() {
+    strict_limits();
+    let addr = server("127.0.0.1:0", router(app::vial_add_to_router));
+    let (head, body) = send(
+        &addr,
+        "POST / HTTP/1.1\r\nHost: x\r\nContent-Length: 2\r\n\r\nhi",
+    );
+    assert!(head.starts_with("HTTP/1.1 200 OK\r\n"), "{}", head);
+    assert_eq!("got hi", body);
+}
+
+#[test]
+fn rejects_smuggling() {
+    strict_limits();
+    let addr = server("127.0.0.1:0", router(app::vial_add_to_router));
+    let (head, body) = send(
+        &addr,
+        "POST / HTTP/1.1\r\nContent-Length: 2\r\nContent-Length: 20\r\n\r\nhi",
+    );
+    assert!(head.starts_with("HTTP/1.1 400 Bad Request\r\n"), "{}", head);
+    assert_eq!("400 Bad Request", body);
+
+    let (head, _) = send(
+        &addr,
+        "POST / HTTP/1.1\r\nContent-Length: 2\r\nTransfer-Encoding: chunked\r\n\r\nhi",
+    );
+    assert!(head.starts_with("HTTP/1.1 400 Bad Request\r\n"), "{}", head);
+}
+
+#[test]
+fn rejects_bad_content_length() {
+    strict_limits();
+    let addr = server("127.0.0.1:0", router(app::vial_add_to_router));
+    let (head, _) = send(&addr, "POST / HTTP/1.1\r\nContent-Length: 2x\r\n\r\nhi");
+    assert!(head.starts_with("HTTP/1.1 400 Bad Request\r\n"), "{}", head);
+}
+
+#[test]
+fn rejects_too_many_headers() {
+    strict_limits();
+    let addr = server("127.0.0.1:0", router(app::vial_add_to_router));
+    let raw = format!("POST / HTTP/1.1\r\n{}\r\n", "X-A: 1\r\n".repeat(6));
+    let (head, _) = send(&addr, &raw);
+    assert!(
+        head.starts_with("HTTP/1.1 431 Request Header Fields Too Large\r\n"),
+        "{}",
+        head
+    );
+}
+
+#[test]
+fn from_reader_uses_default_limits() {
+    strict_limits();
+    let raw = "GET / HTTP/1.1\r\nX-Value: a\x01b\r\n\r\n";
+    let req = Request::from_reader(raw.as_bytes()).unwrap();
+    assert_eq!(Some("a\x01b"), req.header("X-Value").as_deref());
+}
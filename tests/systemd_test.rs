@@ -0,0 +1,102 @@
+#![cfg(unix)]
+
+mod common;
+
+use {
+    common::{connect, read_body, read_head, router},
+    std::{
+        env,
+        io::Write,
+        net::TcpListener,
+        os::unix::{io::AsRawFd, process::CommandExt},
+        process::{Command, Stdio},
+    },
+    vial::{Listener, ListenerKind},
+};
+
+extern "C" {
+    fn dup2(old: i32, new: i32) -> i32;
+}
+
+mod app {
+    use vial::prelude::*;
+
+    routes! {
+        GET "/" => |req| format!("{:?}", req.listener());
+    }
+}
+
+#[test]
+fn not_activated() {
+    if env::var("VIAL_SYSTEMD_CHILD").is_ok() {
+        return;
+    }
+    env::remove_var("LISTEN_PID");
+    assert!(Listener::systemd().is_err());
+}
+
+/// Runs in a child process started by `socket_activation`, which
+/// hands it a socket on file descriptor 3 like systemd would.
+#[test]
+fn activated_child() {
+    if env::var("VIAL_SYSTEMD_CHILD").is_err() {
+        return;
+    }
+
+    // passed to some other process
+    env::set_var("LISTEN_PID", "1");
+    assert!(Listener::systemd().is_err());
+
+    env::set_var("LISTEN_PID", std::process::id().to_string());
+    let listener = Listener::systemd().unwrap();
+    assert_eq!(ListenerKind::Tcp, listener.kind());
+    assert!(env::var("LISTEN_PID").is_err());
+    assert!(env::var("LISTEN_FDS").is_err());
+
+    vial::run(listener, router(app::vial_add_to_router), Some("")).unwrap()
+}
+
+#[test]
+fn socket_activation() {
+    if env::var("VIAL_SYSTEMD_CHILD").is_ok() {
+        return;
+    }
+
+    let socket = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = socket.local_addr().unwrap();
+    // a copy of the socket, so dup2() never has to copy a descriptor
+    // onto itself and keep its close-on-exec flag
+    let copy = socket.try_clone().unwrap();
+    let fd = copy.as_raw_fd();
+    let mut child = unsafe {
+        Command::new(env::current_exe().unwrap())
+            .args(["--exact", "activated_child", "--nocapture"])
+            .env("VIAL_SYSTEMD_CHILD", "1")
+            .env("LISTEN_FDS", "1")
+            .stdout(Stdio::null())
+            .pre_exec(move || {
+                if dup2(fd, 3) == 3 {
+                    Ok(())
+                } else {
+                    Err(std::io::Error::last_os_error())
+                }
+            })
+            .spawn()
+            .unwrap()
+    };
+    // only the child has it now
+    drop(copy);
+    drop(socket);
+
+    let mut client = connect(&addr.to_string());
+    client
+        .write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\n\r\n")
+        .unwrap();
+    let head = read_head(&mut client);
+    let body = read_body(&mut client, &head);
+    child.kill().unwrap();
+    child.wait().unwrap();
+
+    assert!(head.starts_with("HTTP/1.1 200 "), "{}", head);
+    assert_eq!("Tcp", body);
+}
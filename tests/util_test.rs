@@ -35,7 +35,7 @@ fn content_type() {
         util::content_type("MANUAL.markdown"),
         "text/plain; charset=utf8"
     );
-    assert_eq!(util::content_type("?"), "text/plain; charset=utf8");
+    assert_eq!(util::content_type("?"), "application/octet-stream");
 }
 
 #[test]
@@ -44,6 +44,27 @@ fn decode_form_value() {
         util::decode_form_value("Well%2C+that%27s+just+great%21"),
         "Well, that's just great!"
     );
+    assert_eq!(util::decode_form_value("%aé"), "");
+}
+
+#[test]
+fn percent_decode() {
+    assert_eq!(Some("café".into()), util::percent_decode("caf%C3%A9"));
+    assert_eq!(Some("café".into()), util::percent_decode("café"));
+    // a multibyte character right after the `%`
+    assert_eq!(None, util::percent_decode("%aé"));
+    assert_eq!(None, util::percent_decode("%éa"));
+    assert_eq!(None, util::percent_decode("%+a"));
+    assert_eq!(None, util::percent_decode("100%"));
+    assert_eq!(None, util::percent_decode("%FF"));
+}
+
+#[test]
+fn reason_phrase() {
+    assert_eq!("OK", util::reason_phrase(200));
+    assert_eq!("Not Found", util::reason_phrase(404));
+    assert_eq!("Request Header Fields Too Large", util::reason_phrase(431));
+    assert_eq!("", util::reason_phrase(599));
 }
 
 #[test]
@@ -57,13 +78,50 @@ fn http_current_date() {
 
 #[test]
 fn file_size() {
-    #[cfg(target_family = "windows")] {
+    #[cfg(target_family = "windows")]
+    {
         assert_eq!(1072, util::file_size("LICENSE-MIT"));
         assert_eq!(25835, util::file_size("tests/assets/rfcs/rfc1288.txt"));
     }
-    #[cfg(target_family = "unix")] {
+    #[cfg(target_family = "unix")]
+    {
         assert_eq!(1052, util::file_size("LICENSE-MIT"));
         assert_eq!(25161, util::file_size("tests/assets/rfcs/rfc1288.txt"));
     }
     assert_eq!(0, util::file_size("LICENSE-MADE-UP"));
 }
+
+#[test]
+fn base64() {
+    assert_eq!("", util::base64(b""));
+    assert_eq!("Zg==", util::base64(b"f"));
+    assert_eq!("Zm8=", util::base64(b"fo"));
+    assert_eq!("Zm9v", util::base64(b"foo"));
+    assert_eq!("Zm9vYmFy", util::base64(b"foobar"));
+    assert_eq!("-_8", util::base64_url(&[0xfb, 0xff]));
+    assert_eq!("+/8=", util::base64(&[0xfb, 0xff]));
+    assert_eq!(Some(b"foobar".to_vec()), util::base64_decode("Zm9vYmFy"));
+    assert_eq!(Some(b"fo".to_vec()), util::base64_decode("Zm8="));
+    assert_eq!(Some(b"f".to_vec()), util::base64_decode("Zg"));
+    assert_eq!(None, util::base64_decode("Zm9v!"));
+    assert_eq!(None, util::base64_decode("Zm9vY"));
+}
+
+#[test]
+fn parse_cookies() {
+    assert_eq!(
+        vec![
+            ("Count".to_string(), "50".to_string()),
+            ("name".to_string(), "Ricardo Lopez".to_string())
+        ],
+        util::parse_cookies("Count=50; name=Ricardo%20Lopez")
+    );
+    assert_eq!(
+        vec![("a".to_string(), "".to_string())],
+        util::parse_cookies(";; a=; =b; c d=e; junk")
+    );
+    assert_eq!(
+        vec![("a".to_string(), "%aé".to_string())],
+        util::parse_cookies("a=%aé")
+    );
+}
@@ -0,0 +1,54 @@
+mod common;
+
+use {
+    common::{router, send, server},
+    vial::{test::Client, Version},
+};
+
+mod app {
+    use vial::prelude::*;
+
+    routes! {
+        GET "/" => |req| format!("{} {}", req.version(), req.host().unwrap_or_default());
+    }
+}
+
+#[test]
+fn answers_in_the_requests_version() {
+    let addr = server("127.0.0.1:0", router(app::vial_add_to_router));
+    let (head, body) = send(&addr, "GET / HTTP/1.0\r\n\r\n");
+    assert!(head.starts_with("HTTP/1.0 200 "), "{}", head);
+    assert!(head.contains("Connection: close\r\n"));
+    assert_eq!("HTTP/1.0 ", body);
+
+    let (head, body) = send(&addr, "GET / HTTP/1.1\r\nHost: example.com\r\n\r\n");
+    assert!(head.starts_with("HTTP/1.1 200 "), "{}", head);
+    assert_eq!("HTTP/1.1 example.com", body);
+}
+
+#[test]
+fn absolute_form() {
+    let addr = server("127.0.0.1:0", router(app::vial_add_to_router));
+    let (head, body) = send(
+        &addr,
+        "GET http://example.com:8080/ HTTP/1.1\r\nHost: other\r\n\r\n",
+    );
+    assert!(head.starts_with("HTTP/1.1 200 "), "{}", head);
+    assert_eq!("HTTP/1.1 example.com:8080", body);
+}
+
+#[test]
+fn options_asterisk() {
+    let addr = server("127.0.0.1:0", router(app::vial_add_to_router));
+    let (head, _) = send(&addr, "OPTIONS * HTTP/1.1\r\nHost: example.com\r\n\r\n");
+    assert!(head.starts_with("HTTP/1.1 200 "), "{}", head);
+    assert!(head.contains("Allow: GET, HEAD, POST, PUT, PATCH, DELETE, OPTIONS\r\n"));
+
+    let (head, _) = send(&addr, "GET * HTTP/1.1\r\nHost: example.com\r\n\r\n");
+    assert!(head.starts_with("HTTP/1.1 400 "), "{}", head);
+
+    let client = Client::new(router(app::vial_add_to_router));
+    let res = client.options("*").send();
+    assert_eq!(200, res.code());
+    assert_eq!(Version::HTTP11, res.version());
+}
@@ -0,0 +1,202 @@
+#![cfg(feature = "websocket")]
+
+mod common;
+
+use {
+    common::{connect, read_head, router, server},
+    std::{
+        io::{Read, Write},
+        net::{TcpListener, TcpStream},
+    },
+    vial::{
+        websocket::{self, Message, WebSocket},
+        Error, Request,
+    },
+};
+
+/// Connected (client, server) sockets.
+fn pair() -> (TcpStream, WebSocket) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+    let (server, _) = listener.accept().unwrap();
+    (client, WebSocket::new(server))
+}
+
+/// A masked frame, like a browser would send.
+fn frame(fin: bool, opcode: u8, payload: &[u8]) -> Vec<u8> {
+    let mask = [1, 2, 3, 4];
+    let mut out = vec![if fin { 0x80 } else { 0 } | opcode];
+    if payload.len() < 126 {
+        out.push(0x80 | payload.len() as u8);
+    } else {
+        out.push(0x80 | 126);
+        out.extend_from_slice(&(payload.len() as u16).to_be_bytes());
+    }
+    out.extend_from_slice(&mask);
+    out.extend(payload.iter().enumerate().map(|(i, b)| b ^ mask[i % 4]));
+    out
+}
+
+/// Read one unmasked frame sent by the server.
+fn read_frame(client: &mut TcpStream) -> (u8, Vec<u8>) {
+    let mut head = [0u8; 2];
+    client.read_exact(&mut head).unwrap();
+    assert_eq!(0, head[1] & 0x80, "server frames aren't masked");
+    let len = match head[1] {
+        126 => {
+            let mut len = [0u8; 2];
+            client.read_exact(&mut len).unwrap();
+            u16::from_be_bytes(len) as usize
+        }
+        len => len as usize,
+    };
+    let mut payload = vec![0u8; len];
+    client.read_exact(&mut payload).unwrap();
+    (head[0] & 0x0F, payload)
+}
+
+#[test]
+fn accept_key() {
+    assert_eq!(
+        "s3pPLMBiTxaQ9kYGzzhZRbK+xOo=",
+        websocket::accept_key("dGhlIHNhbXBsZSBub25jZQ==")
+    );
+}
+
+#[test]
+fn upgrade_key() {
+    let req = Request::builder()
+        .path("/chat")
+        .header("Upgrade", "websocket")
+        .header("Connection", "keep-alive, Upgrade")
+        .header("Sec-WebSocket-Version", "13")
+        .header("Sec-WebSocket-Key", "dGhlIHNhbXBsZSBub25jZQ==")
+        .build()
+        .unwrap();
+    assert_eq!(
+        Some("dGhlIHNhbXBsZSBub25jZQ==".to_string()),
+        websocket::upgrade_key(&req)
+    );
+
+    let req = req.with_header("Sec-WebSocket-Version", "8");
+    assert_eq!(None, websocket::upgrade_key(&req));
+    assert_eq!(None, websocket::upgrade_key(&Request::default()));
+}
+
+#[test]
+fn messages() {
+    let (mut client, mut ws) = pair();
+
+    client.write_all(&frame(true, 0x1, b"Hello")).unwrap();
+    assert_eq!(Message::Text("Hello".into()), ws.recv().unwrap());
+
+    let big = vec![7u8; 300];
+    client.write_all(&frame(true, 0x2, &big)).unwrap();
+    assert_eq!(Message::Binary(big.clone()), ws.recv().unwrap());
+
+    ws.send_text("Hi back").unwrap();
+    assert_eq!((0x1, b"Hi back".to_vec()), read_frame(&mut client));
+    ws.send_binary(&big).unwrap();
+    assert_eq!((0x2, big), read_frame(&mut client));
+}
+
+#[test]
+fn fragments_and_pings() {
+    let (mut client, mut ws) = pair();
+
+    // a ping in the middle of a fragmented message
+    client.write_all(&frame(false, 0x1, b"Hel")).unwrap();
+    client.write_all(&frame(true, 0x9, b"ping!")).unwrap();
+    client.write_all(&frame(false, 0x0, b"lo, ")).unwrap();
+    client.write_all(&frame(true, 0x0, b"world")).unwrap();
+
+    assert_eq!(Message::Ping(b"ping!".to_vec()), ws.recv().unwrap());
+    assert_eq!((0xA, b"ping!".to_vec()), read_frame(&mut client));
+    assert_eq!(Message::Text("Hello, world".into()), ws.recv().unwrap());
+
+    ws.ping(b"you there?").unwrap();
+    assert_eq!((0x9, b"you there?".to_vec()), read_frame(&mut client));
+    client.write_all(&frame(true, 0xA, b"yep")).unwrap();
+    assert_eq!(Message::Pong(b"yep".to_vec()), ws.recv().unwrap());
+}
+
+#[test]
+fn close() {
+    let (mut client, mut ws) = pair();
+
+    let mut payload = 1001u16.to_be_bytes().to_vec();
+    payload.extend_from_slice(b"bye");
+    client.write_all(&frame(true, 0x8, &payload)).unwrap();
+    assert_eq!(
+        Message::Close(Some((1001, "bye".into()))),
+        ws.recv().unwrap()
+    );
+    assert_eq!(
+        (0x8, 1001u16.to_be_bytes().to_vec()),
+        read_frame(&mut client)
+    );
+    assert!(ws.is_closed());
+    assert_eq!(Err(Error::ConnectionClosed), ws.recv());
+    assert_eq!(Err(Error::ConnectionClosed), ws.send_text("too late"));
+}
+
+#[test]
+fn protocol_errors() {
+    let (mut client, mut ws) = pair();
+    // unmasked frame
+    client.write_all(&[0x81, 0x02, b'h', b'i']).unwrap();
+    assert!(matches!(ws.recv(), Err(Error::WebSocket(..))));
+    let (opcode, payload) = read_frame(&mut client);
+    assert_eq!(0x8, opcode);
+    assert_eq!(1002u16.to_be_bytes(), payload[..2]);
+
+    let (mut client, mut ws) = pair();
+    client.write_all(&frame(true, 0x1, &[0xFF, 0xFE])).unwrap();
+    assert!(matches!(ws.recv(), Err(Error::WebSocket(..))));
+    assert_eq!(1007u16.to_be_bytes(), read_frame(&mut client).1[..2]);
+
+    let (mut client, mut ws) = pair();
+    ws.set_max_message_size(4);
+    client.write_all(&frame(true, 0x1, b"too long")).unwrap();
+    assert!(matches!(ws.recv(), Err(Error::WebSocket(..))));
+    assert_eq!(1009u16.to_be_bytes(), read_frame(&mut client).1[..2]);
+}
+
+mod app {
+    use vial::{prelude::*, websocket::Message};
+
+    routes! {
+        GET "/echo" => |_| Response::websocket(|mut ws| {
+            while let Ok(msg) = ws.recv() {
+                if let Message::Text(text) = msg {
+                    ws.send_text(&text.to_uppercase()).unwrap();
+                }
+            }
+        });
+    }
+}
+
+#[test]
+fn server_upgrade() {
+    let addr = server("127.0.0.1:0", router(app::vial_add_to_router));
+    let mut client = connect(&addr);
+    client
+        .write_all(
+            b"GET /echo HTTP/1.1\r\nHost: localhost\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Version: 13\r\nSec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n\r\n",
+        )
+        .unwrap();
+
+    let head = read_head(&mut client);
+    assert!(head.starts_with("HTTP/1.1 101 Switching Protocols\r\n"));
+    assert!(head.contains("\r\nSec-WebSocket-Accept: s3pPLMBiTxaQ9kYGzzhZRbK+xOo=\r\n"));
+
+    client.write_all(&frame(true, 0x1, b"shout")).unwrap();
+    assert_eq!((0x1, b"SHOUT".to_vec()), read_frame(&mut client));
+
+    // plain requests to a WebSocket route are turned away
+    let mut client = connect(&addr);
+    client
+        .write_all(b"GET /echo HTTP/1.1\r\nHost: localhost\r\n\r\n")
+        .unwrap();
+    assert!(read_head(&mut client).starts_with("HTTP/1.1 400"));
+}